@@ -1,41 +1,676 @@
+use crate::cache::format_size;
+use crate::config::config;
+use bzip2::bufread::MultiBzDecoder;
+use eyre::{bail, eyre};
 use flate2::bufread::GzDecoder;
 use log::debug;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
-use tar::Archive;
+use lzma_rust2::XzReader;
+use ruzstd::decoding::StreamingDecoder;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use tar::{Archive, EntryType};
 use zip::ZipArchive;
 
-pub fn extract_tgz(file: &Path, work_dir: &Path) -> color_eyre::Result<()> {
-    let archive_file = File::open(file)?;
-    let input = BufReader::new(archive_file);
+/// How the install dir is found in an extracted archive, set per tool with
+/// `tool_roots.<tool>` in the configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RootStrategy {
+    /// The archive root if it has `bin/`, else its only directory (or that
+    /// directory's `Contents/Home` for a macOS bundle), else the one directory
+    /// that has `bin/`.
+    #[default]
+    Auto,
+    /// Strip this many leading directories, like `tar --strip-components`.
+    Strip(usize),
+    /// The first directory holding `bin/`, however deep.
+    Bin,
+    /// The archive root itself (`strip=0`).
+    Archive,
+}
+
+impl FromStr for RootStrategy {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "auto" => RootStrategy::Auto,
+            "bin" => RootStrategy::Bin,
+            "archive" => RootStrategy::Archive,
+            _ => match s.strip_prefix("strip=").map(str::parse) {
+                Some(Ok(n)) => RootStrategy::Strip(n),
+                _ => bail!("unknown root strategy '{s}' (expected auto, bin, archive or strip=N)"),
+            },
+        })
+    }
+}
+
+impl TryFrom<String> for RootStrategy {
+    type Error = eyre::Report;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl Display for RootStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RootStrategy::Auto => write!(f, "auto"),
+            RootStrategy::Strip(n) => write!(f, "strip={n}"),
+            RootStrategy::Bin => write!(f, "bin"),
+            RootStrategy::Archive => write!(f, "archive"),
+        }
+    }
+}
+
+impl From<RootStrategy> for String {
+    fn from(strategy: RootStrategy) -> String {
+        strategy.to_string()
+    }
+}
+
+/// The directory of the archive extracted in `work_dir` to install.
+pub fn find_root(work_dir: &Path, strategy: RootStrategy) -> color_eyre::Result<PathBuf> {
+    let root = match strategy {
+        RootStrategy::Archive => work_dir.to_path_buf(),
+        RootStrategy::Strip(n) => {
+            let mut dir = work_dir.to_path_buf();
+            for _ in 0..n {
+                dir = match subdirs(&dir)?.as_slice() {
+                    [only] => only.clone(),
+                    dirs => bail!(
+                        "can't strip {dir:?}: expected one directory, found {}",
+                        dirs.len()
+                    ),
+                };
+            }
+            dir
+        }
+        RootStrategy::Bin => find_bin_dir(work_dir)?
+            .ok_or_else(|| eyre!("no directory with a bin/ in {work_dir:?}"))?,
+        RootStrategy::Auto => {
+            if has_bin(work_dir) {
+                work_dir.to_path_buf()
+            } else {
+                match subdirs(work_dir)?.as_slice() {
+                    [] => bail!("no directory found in {work_dir:?}"),
+                    [only] if only.join("Contents").join("Home").is_dir() => {
+                        only.join("Contents").join("Home")
+                    }
+                    [only] => only.clone(),
+                    dirs => {
+                        let with_bin: Vec<_> = dirs.iter().filter(|d| has_bin(d)).collect();
+                        match with_bin.as_slice() {
+                            [only] => only.to_path_buf(),
+                            _ => bail!(
+                                "found {} directories in {work_dir:?} and can't tell which one to install; \
+                                set tool_roots.<tool> in the configuration",
+                                dirs.len()
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+    };
+    debug!("install root for {strategy} is {root:?}");
+    Ok(root)
+}
+
+fn has_bin(dir: &Path) -> bool {
+    dir.join("bin").is_dir()
+}
+
+/// Subdirectories of `dir`, sorted so that results don't depend on the
+/// filesystem's order.
+fn subdirs(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Breadth-first search for the shallowest directory holding `bin/`.
+fn find_bin_dir(dir: &Path) -> std::io::Result<Option<PathBuf>> {
+    let mut level = vec![dir.to_path_buf()];
+    while !level.is_empty() {
+        if let Some(found) = level.iter().find(|d| has_bin(d)) {
+            return Ok(Some(found.clone()));
+        }
+        let mut next = vec![];
+        for d in &level {
+            next.extend(subdirs(d)?);
+        }
+        level = next;
+    }
+    Ok(None)
+}
+
+/// The kinds of archives tools are published as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarBz2,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// Recognise a format from the first bytes of a file (at least 262 of them
+    /// to spot an uncompressed tar).
+    pub fn from_magic(head: &[u8]) -> Option<ArchiveFormat> {
+        Some(match head {
+            [b'P', b'K', 3, 4, ..] | [b'P', b'K', 5, 6, ..] => ArchiveFormat::Zip,
+            [0x1f, 0x8b, ..] => ArchiveFormat::TarGz,
+            [0xfd, b'7', b'z', b'X', b'Z', 0, ..] => ArchiveFormat::TarXz,
+            [b'B', b'Z', b'h', ..] => ArchiveFormat::TarBz2,
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => ArchiveFormat::TarZst,
+            _ if head.get(257..262) == Some(b"ustar") => ArchiveFormat::Tar,
+            _ => return None,
+        })
+    }
+
+    /// Recognise a format from a file name's extension
+    /// (`OpenJDK21U-jdk_x64_linux_hotspot_21.0.6_7.tar.gz`).
+    pub fn from_file_name(name: &str) -> Option<ArchiveFormat> {
+        let name = name.to_ascii_lowercase();
+        [
+            (".zip", ArchiveFormat::Zip),
+            (".tar.gz", ArchiveFormat::TarGz),
+            (".tgz", ArchiveFormat::TarGz),
+            (".tar.xz", ArchiveFormat::TarXz),
+            (".txz", ArchiveFormat::TarXz),
+            (".tar.bz2", ArchiveFormat::TarBz2),
+            (".tbz2", ArchiveFormat::TarBz2),
+            (".tar.zst", ArchiveFormat::TarZst),
+            (".tzst", ArchiveFormat::TarZst),
+            (".tar", ArchiveFormat::Tar),
+        ]
+        .into_iter()
+        .find_map(|(ext, format)| name.ends_with(ext).then_some(format))
+    }
+
+    /// The format of `file`, from its content or else from `file_name`, the
+    /// name it was downloaded as.
+    pub fn detect(file: &Path, file_name: &str) -> color_eyre::Result<ArchiveFormat> {
+        let mut head = Vec::with_capacity(512);
+        File::open(file)?.take(512).read_to_end(&mut head)?;
+        match ArchiveFormat::from_magic(&head).or_else(|| ArchiveFormat::from_file_name(file_name))
+        {
+            Some(format) => Ok(format),
+            None => bail!("{file:?} ({file_name}) is not a zip or tar archive"),
+        }
+    }
+}
+
+/// Caps on what one archive may extract, against decompression bombs. Set
+/// with `max_extract_files` and `max_extract_size` in the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_files: u64,
+    /// Total bytes of extracted file content.
+    pub max_size: u64,
+}
+
+impl Default for Limits {
+    /// Well above the largest SDKs (a GraalVM JDK is ~1 GiB in ~3k files).
+    fn default() -> Limits {
+        Limits {
+            max_files: 200_000,
+            max_size: 8 << 30,
+        }
+    }
+}
+
+impl Limits {
+    pub fn configured() -> Limits {
+        let defaults = Limits::default();
+        let config = config();
+        Limits {
+            max_files: config.max_extract_files.unwrap_or(defaults.max_files),
+            max_size: config
+                .max_extract_size
+                .map_or(defaults.max_size, |size| size.0),
+        }
+    }
+}
+
+/// Extract `file`, a `format` archive, into `work_dir`. Entries with absolute
+/// paths or `..`, links pointing outside `work_dir`, and archives going over
+/// `limits` are refused, naming the offending entry.
+pub fn extract(
+    file: &Path,
+    format: ArchiveFormat,
+    work_dir: &Path,
+    limits: &Limits,
+) -> color_eyre::Result<()> {
+    debug!("extracting {file:?} as {format:?}");
+    if format == ArchiveFormat::Zip {
+        return extract_zip(file, work_dir, limits);
+    }
+    unpack_tar_format(BufReader::new(File::open(file)?), format, work_dir, limits)
+}
 
-    let decoder = GzDecoder::new(input);
-    let mut archive = Archive::new(decoder);
+/// Extract a tarball from `input` as it arrives, e.g. while downloading it,
+/// with the same checks as `extract`. The format is told by the first bytes,
+/// else by `file_name`. Returns false, having read only those first bytes,
+/// when `input` is not a tarball: zips can only be read from a complete file.
+pub fn extract_stream(
+    input: &mut dyn Read,
+    file_name: &str,
+    work_dir: &Path,
+    limits: &Limits,
+) -> color_eyre::Result<bool> {
+    let mut head = Vec::with_capacity(512);
+    (&mut *input).take(512).read_to_end(&mut head)?;
+    let format =
+        ArchiveFormat::from_magic(&head).or_else(|| ArchiveFormat::from_file_name(file_name));
+    match format {
+        Some(ArchiveFormat::Zip) | None => Ok(false),
+        Some(format) => {
+            debug!("extracting {file_name} as {format:?} while downloading");
+            let input = BufReader::new(head.as_slice().chain(input));
+            unpack_tar_format(input, format, work_dir, limits)?;
+            Ok(true)
+        }
+    }
+}
+
+fn unpack_tar_format(
+    input: impl BufRead,
+    format: ArchiveFormat,
+    work_dir: &Path,
+    limits: &Limits,
+) -> color_eyre::Result<()> {
+    match format {
+        ArchiveFormat::Zip => bail!("zip archives can't be read as a stream"),
+        ArchiveFormat::Tar => unpack_tar(input, work_dir, limits),
+        ArchiveFormat::TarGz => unpack_tar(GzDecoder::new(input), work_dir, limits),
+        ArchiveFormat::TarXz => unpack_tar(XzReader::new(input, true), work_dir, limits),
+        ArchiveFormat::TarBz2 => unpack_tar(MultiBzDecoder::new(input), work_dir, limits),
+        ArchiveFormat::TarZst => unpack_tar(
+            StreamingDecoder::new(input).map_err(|e| eyre!("invalid zstd stream: {e}"))?,
+            work_dir,
+            limits,
+        ),
+    }
+}
+
+fn unpack_tar(input: impl Read, work_dir: &Path, limits: &Limits) -> color_eyre::Result<()> {
+    let mut archive = Archive::new(input);
     #[cfg(unix)]
     archive.set_preserve_permissions(true);
-    // #[cfg(unix)]
-    // archive.set_preserve_mtime(true);
-    // // #[cfg(unix)]
-    // archive.set_ignore_zeros(true);
-
-    archive.unpack(work_dir)?;
+    fs::create_dir_all(work_dir)?;
+    let root = fs::canonicalize(work_dir)?;
+    let mut budget = Budget::new(limits);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let name = path.display().to_string();
+        let rel = safe_path(&path).map_err(|reason| refuse(&name, reason))?;
+        budget.add_file(&name)?;
+        let out = work_dir.join(&rel);
+        refuse_existing_symlink(&out).map_err(|reason| refuse(&name, reason))?;
+        match entry.header().entry_type() {
+            EntryType::Symlink => {
+                let target = entry
+                    .link_name()?
+                    .ok_or_else(|| refuse(&name, "symlink without a target"))?;
+                check_symlink(&root, &rel, &target).map_err(|reason| refuse(&name, reason))?;
+            }
+            EntryType::Link => {
+                let target = entry
+                    .link_name()?
+                    .ok_or_else(|| refuse(&name, "hard link without a target"))?;
+                safe_path(&target).map_err(|reason| refuse(&name, reason))?;
+            }
+            // Refuse before writing when the declared size alone is too much.
+            _ => budget.check_size(&name, entry.size())?,
+        }
+        if !entry.unpack_in(work_dir)? {
+            return Err(refuse(&name, "outside the extraction dir"));
+        }
+        // Sparse entries expand beyond their declared size: count the file.
+        if let Ok(written) = fs::symlink_metadata(&out) {
+            if written.is_file() {
+                budget.add_size(&name, written.len())?;
+            }
+        }
+    }
     Ok(())
 }
 
-pub fn extract_zip(file: &Path, work_dir: &Path) -> color_eyre::Result<()> {
+pub fn extract_zip(file: &Path, work_dir: &Path, limits: &Limits) -> color_eyre::Result<()> {
     debug!("unzipping");
     let archive_file = File::open(file)?;
     let mut archive = ZipArchive::new(archive_file)?;
-    archive.extract(work_dir)?;
+    fs::create_dir_all(work_dir)?;
+    let root = fs::canonicalize(work_dir)?;
+    let mut budget = Budget::new(limits);
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let name = entry.name().to_string();
+        let rel = safe_path(Path::new(&name)).map_err(|reason| refuse(&name, reason))?;
+        budget.add_file(&name)?;
+        let out = work_dir.join(&rel);
+        if entry.is_dir() {
+            fs::create_dir_all(&out)?;
+            continue;
+        }
+        if let Some(parent) = out.parent() {
+            fs::create_dir_all(parent)?;
+            // Links checked one by one can still escape together (`a -> ..`
+            // then `a/b -> ..`), so check where the entry really lands.
+            if !fs::canonicalize(parent)?.starts_with(&root) {
+                return Err(refuse(&name, "outside the extraction dir"));
+            }
+        }
+        refuse_existing_symlink(&out).map_err(|reason| refuse(&name, reason))?;
+        if entry.is_symlink() {
+            let mut target = String::new();
+            entry.by_ref().take(4096).read_to_string(&mut target)?;
+            check_symlink(&root, &rel, Path::new(&target))
+                .map_err(|reason| refuse(&name, reason))?;
+            symlink::symlink_auto(&target, &out)?;
+            continue;
+        }
+        let mut file = File::create(&out)?;
+        // Never trust the declared size: stop one byte past the budget.
+        let written = std::io::copy(&mut entry.by_ref().take(budget.remaining() + 1), &mut file)?;
+        budget.add_size(&name, written)?;
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&out, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+    }
+    Ok(())
+}
+
+/// What an archive has extracted so far, against its `Limits`.
+struct Budget {
+    limits: Limits,
+    files: u64,
+    size: u64,
+}
+
+impl Budget {
+    fn new(limits: &Limits) -> Budget {
+        Budget {
+            limits: *limits,
+            files: 0,
+            size: 0,
+        }
+    }
+
+    fn add_file(&mut self, name: &str) -> color_eyre::Result<()> {
+        self.files += 1;
+        if self.files > self.limits.max_files {
+            let max = self.limits.max_files;
+            return Err(refuse(
+                name,
+                &format!("more than {max} files (max_extract_files)"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn add_size(&mut self, name: &str, bytes: u64) -> color_eyre::Result<()> {
+        self.check_size(name, bytes)?;
+        self.size += bytes;
+        Ok(())
+    }
+
+    /// Fail if `bytes` more would go over the size limit.
+    fn check_size(&self, name: &str, bytes: u64) -> color_eyre::Result<()> {
+        if bytes > self.remaining() {
+            let max = format_size(self.limits.max_size);
+            return Err(refuse(
+                name,
+                &format!("more than {max} extracted (max_extract_size)"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn remaining(&self) -> u64 {
+        self.limits.max_size.saturating_sub(self.size)
+    }
+}
+
+fn refuse(name: &str, reason: &str) -> eyre::Report {
+    eyre!("refusing to extract archive entry {name:?}: {reason}")
+}
+
+/// `path` as a relative path inside the extraction dir, rejecting absolute
+/// paths and `..`.
+fn safe_path(path: &Path) -> Result<PathBuf, &'static str> {
+    let mut safe = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => safe.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => return Err("path contains '..'"),
+            Component::RootDir | Component::Prefix(_) => return Err("absolute path"),
+        }
+    }
+    Ok(safe)
+}
+
+/// Check that a symlink at `rel` (relative to the extraction dir, `root` once
+/// canonicalized) pointing at `target` stays inside the extraction dir:
+/// lexically, and once the links already extracted on the way are followed.
+fn check_symlink(root: &Path, rel: &Path, target: &Path) -> Result<(), &'static str> {
+    const OUTSIDE: &str = "symlink points outside the extraction dir";
+    let mut depth: usize = rel.parent().map_or(0, |p| p.components().count());
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => depth = depth.checked_sub(1).ok_or(OUTSIDE)?,
+            Component::RootDir | Component::Prefix(_) => return Err("symlink to an absolute path"),
+        }
+    }
+    let parent = rel.parent().unwrap_or(Path::new(""));
+    let resolved = resolve(root, &parent.join(target)).map_err(|_| OUTSIDE)?;
+    if !resolved.starts_with(root) {
+        return Err(OUTSIDE);
+    }
     Ok(())
 }
 
+/// Where `path` leads from `dir`, following the links that exist so far.
+/// Components that don't exist yet are taken as they are.
+fn resolve(dir: &Path, path: &Path) -> std::io::Result<PathBuf> {
+    let mut resolved = fs::canonicalize(dir)?;
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => {
+                resolved.push(part);
+                if fs::symlink_metadata(&resolved).is_ok() {
+                    resolved = fs::canonicalize(&resolved)?;
+                }
+            }
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            _ => {}
+        }
+    }
+    Ok(resolved)
+}
+
+/// Refuse to write an entry through a link extracted before it.
+fn refuse_existing_symlink(out: &Path) -> Result<(), &'static str> {
+    match fs::symlink_metadata(out) {
+        Ok(meta) if meta.file_type().is_symlink() => Err("would overwrite a symlink"),
+        _ => Ok(()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Write;
 
+    #[test]
+    fn finds_install_root_in_common_layouts() {
+        let tmp = std::env::temp_dir().join(format!("rsdk-roottest-{}", uuid::Uuid::new_v4()));
+        let mkdirs = |rel: &str| std::fs::create_dir_all(tmp.join(rel)).unwrap();
+
+        // A README next to the tool dir.
+        mkdirs("readme/tool-1.0/bin");
+        std::fs::write(tmp.join("readme/README"), "read me").unwrap();
+        assert_eq!(
+            find_root(&tmp.join("readme"), RootStrategy::Auto).unwrap(),
+            tmp.join("readme/tool-1.0")
+        );
+
+        // A flat archive.
+        mkdirs("flat/bin");
+        mkdirs("flat/lib");
+        assert_eq!(
+            find_root(&tmp.join("flat"), RootStrategy::Auto).unwrap(),
+            tmp.join("flat")
+        );
+
+        // A macOS JDK bundle.
+        mkdirs("bundle/jdk-21.jdk/Contents/Home/bin");
+        assert_eq!(
+            find_root(&tmp.join("bundle"), RootStrategy::Auto).unwrap(),
+            tmp.join("bundle/jdk-21.jdk/Contents/Home")
+        );
+
+        // Several dirs, only one with bin/.
+        mkdirs("many/docs");
+        mkdirs("many/tool/bin");
+        assert_eq!(
+            find_root(&tmp.join("many"), RootStrategy::Auto).unwrap(),
+            tmp.join("many/tool")
+        );
+
+        mkdirs("deep/a/b/bin");
+        assert_eq!(
+            find_root(&tmp.join("deep"), RootStrategy::Strip(2)).unwrap(),
+            tmp.join("deep/a/b")
+        );
+        assert_eq!(
+            find_root(&tmp.join("deep"), RootStrategy::Bin).unwrap(),
+            tmp.join("deep/a/b")
+        );
+        assert_eq!(
+            find_root(&tmp.join("deep"), RootStrategy::Archive).unwrap(),
+            tmp.join("deep")
+        );
+        assert!(find_root(&tmp.join("many"), RootStrategy::Strip(1)).is_err());
+
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn detects_and_extracts_every_tar_compression() {
+        let tmp = std::env::temp_dir().join(format!("rsdk-tartest-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&tmp).unwrap();
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "tool-1.0/bin/tool", &b"hello"[..])
+            .unwrap();
+        let tar = builder.into_inner().unwrap();
+
+        let gz = {
+            let mut w = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+            w.write_all(&tar).unwrap();
+            w.finish().unwrap()
+        };
+        let xz = {
+            let options = lzma_rust2::XzOptions::with_preset(1);
+            let mut w = lzma_rust2::XzWriter::new(vec![], options).unwrap();
+            w.write_all(&tar).unwrap();
+            w.finish().unwrap()
+        };
+        let bz2 = {
+            let mut w = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::fast());
+            w.write_all(&tar).unwrap();
+            w.finish().unwrap()
+        };
+        let zst = ruzstd::encoding::compress_to_vec(
+            &tar[..],
+            ruzstd::encoding::CompressionLevel::Fastest,
+        );
+
+        for (bytes, format) in [
+            (tar.clone(), ArchiveFormat::Tar),
+            (gz, ArchiveFormat::TarGz),
+            (xz, ArchiveFormat::TarXz),
+            (bz2, ArchiveFormat::TarBz2),
+            (zst, ArchiveFormat::TarZst),
+        ] {
+            let file = tmp.join(format!("{format:?}"));
+            std::fs::write(&file, bytes).unwrap();
+            assert_eq!(ArchiveFormat::detect(&file, "download").unwrap(), format);
+            let out = tmp.join(format!("{format:?}-out"));
+            extract(&file, format, &out, &Limits::default()).unwrap();
+            assert_eq!(
+                std::fs::read_to_string(out.join("tool-1.0/bin/tool")).unwrap(),
+                "hello"
+            );
+        }
+
+        // An old tar without the ustar magic is told by its name.
+        let file = tmp.join("v7");
+        std::fs::write(&file, [0u8; 512]).unwrap();
+        assert_eq!(
+            ArchiveFormat::detect(&file, "tool-1.0.tar").unwrap(),
+            ArchiveFormat::Tar
+        );
+        assert!(ArchiveFormat::detect(&file, "tool-1.0.pkg").is_err());
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn detects_format_from_file_name() {
+        assert_eq!(
+            ArchiveFormat::from_file_name("jdk.TAR.GZ"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_file_name("zulu.tar.xz"),
+            Some(ArchiveFormat::TarXz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_file_name("tool.tzst"),
+            Some(ArchiveFormat::TarZst)
+        );
+        assert_eq!(
+            ArchiveFormat::from_file_name("maven.zip"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(ArchiveFormat::from_file_name("tool"), None);
+    }
+
+    #[test]
+    fn parses_root_strategies() {
+        for s in ["auto", "bin", "archive", "strip=2"] {
+            assert_eq!(s.parse::<RootStrategy>().unwrap().to_string(), s);
+        }
+        assert!("strip=x".parse::<RootStrategy>().is_err());
+        assert!("top".parse::<RootStrategy>().is_err());
+    }
+
     #[test]
     fn extract_zip_deflate_roundtrip() {
         let tmp = std::env::temp_dir().join(format!("rsdk-ziptest-{}", std::process::id()));
@@ -58,7 +693,7 @@ mod tests {
         }
 
         let out = tmp.join("out");
-        extract_zip(&zip_path, &out).unwrap();
+        extract_zip(&zip_path, &out, &Limits::default()).unwrap();
         assert_eq!(
             std::fs::read_to_string(out.join("hello.txt")).unwrap(),
             "hello zip"
@@ -73,8 +708,8 @@ mod tests {
     #[cfg(unix)]
     #[test]
     fn extract_zip_preserves_symlinks() {
-        // JDK distro zips ship symlinks (e.g. bin/java -> ../lib/jexec). zip 8's
-        // extract() must recreate them as real symlinks, not regular files.
+        // JDK distro zips ship symlinks (e.g. bin/java -> ../lib/jexec), which
+        // must be recreated as real symlinks, not regular files.
         let tmp = std::env::temp_dir().join(format!("rsdk-ziptest-symlink-{}", std::process::id()));
         let _ = std::fs::remove_dir_all(&tmp);
         std::fs::create_dir_all(&tmp).unwrap();
@@ -97,7 +732,7 @@ mod tests {
         }
 
         let out = tmp.join("out");
-        extract_zip(&zip_path, &out).unwrap();
+        extract_zip(&zip_path, &out, &Limits::default()).unwrap();
         let link = out.join("jdk/bin/java");
         assert!(
             std::fs::symlink_metadata(&link)
@@ -113,4 +748,237 @@ mod tests {
         );
         std::fs::remove_dir_all(&tmp).unwrap();
     }
+
+    /// A tar holding one entry, with its name written raw so that unsafe
+    /// paths get past the builder's own checks.
+    fn raw_tar(name: &str, kind: tar::EntryType, link: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+        header.set_entry_type(kind);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(vec![]);
+        builder.append(&header, data).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn refuses_entries_escaping_the_extraction_dir() {
+        let tmp = std::env::temp_dir().join(format!("rsdk-unsafe-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&tmp).unwrap();
+        let limits = Limits::default();
+        let extract_tar = |tar: Vec<u8>| {
+            let file = tmp.join("unsafe.tar");
+            std::fs::write(&file, tar).unwrap();
+            extract(&file, ArchiveFormat::Tar, &tmp.join("out"), &limits)
+                .unwrap_err()
+                .to_string()
+        };
+
+        let err = extract_tar(raw_tar(
+            "tool/../../evil",
+            tar::EntryType::Regular,
+            "",
+            b"x",
+        ));
+        assert!(
+            err.contains("\"tool/../../evil\"") && err.contains(".."),
+            "{err}"
+        );
+        let err = extract_tar(raw_tar("/etc/evil", tar::EntryType::Regular, "", b"x"));
+        assert!(err.contains("absolute path"), "{err}");
+        let err = extract_tar(raw_tar(
+            "tool/lib",
+            tar::EntryType::Symlink,
+            "../../..",
+            b"",
+        ));
+        assert!(err.contains("outside the extraction dir"), "{err}");
+        let err = extract_tar(raw_tar(
+            "tool/passwd",
+            tar::EntryType::Symlink,
+            "/etc/passwd",
+            b"",
+        ));
+        assert!(err.contains("absolute path"), "{err}");
+        let err = extract_tar(raw_tar("tool/shadow", tar::EntryType::Link, "../x", b""));
+        assert!(err.contains(".."), "{err}");
+        assert!(!tmp.join("evil").exists());
+
+        let zip_path = tmp.join("unsafe.zip");
+        {
+            let mut w = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+            w.add_symlink(
+                "jdk/bin/java",
+                "../../../bin/sh",
+                zip::write::SimpleFileOptions::default(),
+            )
+            .unwrap();
+            w.finish().unwrap();
+        }
+        let err = extract_zip(&zip_path, &tmp.join("zip-out"), &limits)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("\"jdk/bin/java\""), "{err}");
+        assert!(!tmp.join("zip-out/jdk/bin/java").exists());
+
+        // Each link stays inside on its own, together they lead out.
+        let zip_path = tmp.join("chained.zip");
+        {
+            let mut w = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+            let opts = zip::write::SimpleFileOptions::default();
+            w.add_symlink("d/up", "..", opts).unwrap();
+            w.add_symlink("d/up/out", "..", opts).unwrap();
+            w.start_file("d/up/out/evil", opts).unwrap();
+            w.write_all(b"x").unwrap();
+            w.finish().unwrap();
+        }
+        let err = extract_zip(&zip_path, &tmp.join("chained-out"), &limits)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("\"d/up/out\""), "{err}");
+        assert!(!tmp.join("evil").exists());
+
+        // Lexically inside, but `d/up` leads to the parent on disk.
+        let out = tmp.join("tar-hop-out");
+        let mut tar = raw_tar("d/up", tar::EntryType::Symlink, "..", b"");
+        tar.truncate(tar.len() - 1024);
+        tar.extend(raw_tar("f", tar::EntryType::Symlink, "d/up/../victim", b""));
+        let file = tmp.join("hop.tar");
+        std::fs::write(&file, tar).unwrap();
+        let err = extract(&file, ArchiveFormat::Tar, &out, &limits)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("\"f\"") && err.contains("outside"), "{err}");
+
+        let zip_path = tmp.join("hop.zip");
+        {
+            let mut w = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+            let opts = zip::write::SimpleFileOptions::default();
+            w.add_symlink("d/up", "..", opts).unwrap();
+            w.add_symlink("f", "d/up/../victim", opts).unwrap();
+            w.finish().unwrap();
+        }
+        let err = extract_zip(&zip_path, &tmp.join("zip-hop-out"), &limits)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("\"f\"") && err.contains("outside"), "{err}");
+
+        // A file entry may not be written through a link extracted before it.
+        let zip_path = tmp.join("overwrite.zip");
+        {
+            let mut w = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+            let opts = zip::write::SimpleFileOptions::default();
+            w.add_symlink("jdk/release", "NOTICE", opts).unwrap();
+            w.start_file("jdk/./release", opts).unwrap();
+            w.write_all(b"x").unwrap();
+            w.finish().unwrap();
+        }
+        let err = extract_zip(&zip_path, &tmp.join("overwrite-out"), &limits)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("overwrite a symlink"), "{err}");
+        assert!(!tmp.join("overwrite-out/jdk/NOTICE").exists());
+        let mut tar = raw_tar("jdk/release", tar::EntryType::Symlink, "NOTICE", b"");
+        tar.truncate(tar.len() - 1024);
+        tar.extend(raw_tar("jdk/release", tar::EntryType::Regular, "", b"x"));
+        let file = tmp.join("overwrite.tar");
+        std::fs::write(&file, tar).unwrap();
+        let err = extract(
+            &file,
+            ArchiveFormat::Tar,
+            &tmp.join("tar-overwrite-out"),
+            &limits,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("overwrite a symlink"), "{err}");
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn refuses_archives_over_the_limits() {
+        let tmp = std::env::temp_dir().join(format!("rsdk-limits-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&tmp).unwrap();
+        let zip_path = tmp.join("big.zip");
+        {
+            let mut w = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+            let opts = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+            for name in ["a", "b", "c"] {
+                w.start_file(name, opts).unwrap();
+                w.write_all(&[0u8; 1000]).unwrap();
+            }
+            w.finish().unwrap();
+        }
+        let tar = tmp.join("big.tar");
+        std::fs::write(
+            &tar,
+            raw_tar("big", tar::EntryType::Regular, "", &[0u8; 3000]),
+        )
+        .unwrap();
+
+        let few_files = Limits {
+            max_files: 2,
+            ..Limits::default()
+        };
+        let err = extract_zip(&zip_path, &tmp.join("out1"), &few_files).unwrap_err();
+        assert!(
+            err.to_string().contains("\"c\": more than 2 files"),
+            "{err}"
+        );
+
+        let small = Limits {
+            max_size: 2500,
+            ..Limits::default()
+        };
+        let err = extract_zip(&zip_path, &tmp.join("out2"), &small).unwrap_err();
+        assert!(err.to_string().contains("max_extract_size"), "{err}");
+        let err = extract(&tar, ArchiveFormat::Tar, &tmp.join("out3"), &small).unwrap_err();
+        assert!(err.to_string().contains("max_extract_size"), "{err}");
+
+        extract_zip(&zip_path, &tmp.join("out4"), &Limits::default()).unwrap();
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn extracts_tarballs_as_they_stream() {
+        let tmp = std::env::temp_dir().join(format!("rsdk-stream-{}", uuid::Uuid::new_v4()));
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "tool-1.0/bin/tool", &b"hello"[..])
+            .unwrap();
+        let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+        gz.write_all(&builder.into_inner().unwrap()).unwrap();
+        let gz = gz.finish().unwrap();
+
+        // Told by its content, even when the name says otherwise.
+        let out = tmp.join("out");
+        let streamed = extract_stream(&mut gz.as_slice(), "download.zip", &out, &Limits::default());
+        assert!(streamed.unwrap());
+        assert_eq!(
+            std::fs::read_to_string(out.join("tool-1.0/bin/tool")).unwrap(),
+            "hello"
+        );
+
+        // A zip is left to be extracted once downloaded, after its first bytes.
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        let stored = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("a", stored).unwrap();
+        zip.write_all(&[0u8; 2000]).unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+        let mut input = zip.as_slice();
+        let streamed = extract_stream(&mut input, "", &tmp.join("zip"), &Limits::default());
+        assert!(!streamed.unwrap());
+        assert_eq!(input.len(), zip.len() - 512);
+        assert!(!tmp.join("zip").exists());
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
 }
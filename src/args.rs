@@ -1,5 +1,10 @@
+use crate::cache::{parse_age, parse_size};
+use crate::config::{config, Answer};
 use clap::{Parser, Subcommand, ValueEnum};
+use std::io::IsTerminal;
+use std::path::PathBuf;
 use std::sync::OnceLock;
+use std::time::Duration;
 
 /// CLI Struct for command-line arguments
 #[derive(Parser, Clone)]
@@ -17,27 +22,133 @@ pub struct Cli {
     #[arg(short, long)]
     envout: Option<String>,
 
-    // #[arg(short, long)]
-    // offline: bool,
-    #[arg(long)]
+    /// Root directory for tools, cache and configuration (default ~/.rsdk)
+    #[arg(long, global = true, env = "RSDK_HOME", value_name = "DIR")]
+    home: Option<PathBuf>,
+
+    /// Answer yes to every prompt
+    #[arg(short = 'y', long, global = true, conflicts_with = "no")]
+    yes: bool,
+
+    /// Answer no to every prompt
+    #[arg(long, global = true)]
+    no: bool,
+
+    /// Never prompt: use each prompt's default, fail when there is none
+    #[arg(long, global = true, env = "RSDK_NON_INTERACTIVE")]
+    non_interactive: bool,
+
+    /// Output format: records from list, installed, current and default
+    /// with json or tsv; plain drops colours from any command
+    #[arg(long, global = true, value_name = "FORMAT")]
+    format: Option<Format>,
+
+    /// Only use what is already in the local cache, never the network
+    #[arg(long, global = true, env = "RSDK_OFFLINE")]
+    offline: bool,
+
+    /// Accept invalid TLS certificates (e.g. self-signed proxies)
+    #[arg(long, global = true, env = "RSDK_INSECURE")]
     insecure: bool,
+
+    /// HTTP request timeout
+    #[arg(long, global = true, env = "RSDK_TIMEOUT", value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// How long cached API responses are used before revalidating them
+    #[arg(long, global = true, env = "RSDK_CACHE_TTL", value_name = "SECONDS")]
+    cache_ttl: Option<u64>,
 }
 
 pub static ARGS: OnceLock<Cli> = OnceLock::new();
 
 // Accessors tolerate ARGS being unset (e.g. when the library is used from
 // integration tests) by falling back to defaults rather than panicking.
+// Settings also found in `config.toml` use it when neither the flag nor its
+// environment variable is given.
 pub fn debug() -> bool {
     ARGS.get().map(|c| c.debug).unwrap_or(false)
 }
 
+/// A boolean flag, else the configuration file. clap reads the flag's
+/// environment variable, so a variable set to false also wins over the file.
+fn flag_or_config(flag: impl Fn(&Cli) -> bool, env_var: &str, configured: Option<bool>) -> bool {
+    if ARGS.get().is_some_and(flag) {
+        return true;
+    }
+    if std::env::var_os(env_var).is_some() {
+        return false;
+    }
+    configured.unwrap_or(false)
+}
+
 pub fn insecure() -> bool {
-    ARGS.get().map(|c| c.insecure).unwrap_or(false)
+    flag_or_config(|c| c.insecure, "RSDK_INSECURE", config().insecure)
 }
 
-// pub fn offline() -> bool {
-//     ARGS.get().map(|c| c.offline).unwrap_or(false)
-// }
+pub fn offline() -> bool {
+    flag_or_config(|c| c.offline, "RSDK_OFFLINE", config().offline)
+}
+
+/// Cached API responses younger than this are served without a request.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+pub fn cache_ttl() -> Duration {
+    ARGS.get()
+        .and_then(|c| c.cache_ttl)
+        .or(config().cache_ttl)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_CACHE_TTL)
+}
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn timeout() -> Duration {
+    ARGS.get()
+        .and_then(|c| c.timeout)
+        .or(config().timeout)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT)
+}
+
+/// The answer given for every prompt by `--yes` / `--no`, else by the
+/// `auto_answer` setting.
+pub fn auto_answer() -> Option<Answer> {
+    match ARGS.get() {
+        Some(c) if c.yes => Some(Answer::Yes),
+        Some(c) if c.no => Some(Answer::No),
+        _ => config().auto_answer,
+    }
+}
+
+/// True when prompts must not wait for input: `--non-interactive`,
+/// `RSDK_NON_INTERACTIVE`, or stdin not being a terminal (CI, Docker builds).
+pub fn non_interactive() -> bool {
+    ARGS.get().is_some_and(|c| c.non_interactive) || !std::io::stdin().is_terminal()
+}
+
+/// The installation root given with `--home` or `RSDK_HOME`, if any.
+pub fn home() -> Option<PathBuf> {
+    ARGS.get().and_then(|c| c.home.clone())
+}
+
+/// True if the root came from `--home` itself rather than `RSDK_HOME`.
+pub fn home_from_flag() -> bool {
+    home().is_some() && std::env::var_os("RSDK_HOME").is_none()
+}
+
+/// The structured output format asked for, `None` for human-readable text
+/// (`plain` is the same text without colours).
+pub fn format() -> Option<Format> {
+    ARGS.get()
+        .and_then(|c| c.format)
+        .filter(|f| *f != Format::Plain)
+}
+
+/// `--format plain`: the usual text, without colours.
+pub fn plain() -> bool {
+    ARGS.get().is_some_and(|c| c.format == Some(Format::Plain))
+}
 
 pub fn shell() -> Option<Shell> {
     ARGS.get().and_then(|c| c.shell)
@@ -74,14 +185,56 @@ pub enum Command {
     Env {
         #[command(subcommand)]
         command: Option<EnvSubcommand>,
+        /// Use this file instead of the nearest .sdkmanrc
+        #[arg(long, global = true, value_name = "PATH")]
+        file: Option<PathBuf>,
     },
 
     #[command(about = "List available tools or versions")]
-    List { tool: Option<String> },
+    List {
+        tool: Option<String>,
+        /// Only Java versions of this vendor or distribution (e.g. tem, Temurin)
+        #[arg(long, requires = "tool")]
+        vendor: Option<String>,
+        /// Only Java versions of this feature release (e.g. 21)
+        #[arg(long, requires = "tool")]
+        major: Option<u32>,
+        /// Only long-term support Java releases
+        #[arg(long, requires = "tool")]
+        lts: bool,
+    },
 
     #[command(about = "List installed tools or versions")]
     Installed { tool: Option<String> },
 
+    #[command(about = "List current and default versions with a newer release")]
+    Outdated {
+        tool: Option<String>,
+        /// Consider newer Java feature releases (e.g. 17 to 21)
+        #[arg(long)]
+        any_major: bool,
+        /// Consider Java builds from other vendors
+        #[arg(long)]
+        any_vendor: bool,
+    },
+
+    #[command(about = "Install the newest release of outdated tools")]
+    Upgrade {
+        tool: Option<String>,
+        /// Make the upgraded version the default and current one
+        #[arg(short, long)]
+        default: bool,
+        /// Uninstall the superseded version
+        #[arg(long)]
+        prune: bool,
+        /// Allow moving to a newer Java feature release (e.g. 17 to 21)
+        #[arg(long)]
+        any_major: bool,
+        /// Allow moving to another vendor's Java build
+        #[arg(long)]
+        any_vendor: bool,
+    },
+
     #[command(about = "Set or show the default version for a tool")]
     Default {
         tool: Option<String>,
@@ -92,11 +245,29 @@ pub enum Command {
     Use {
         tool: String,
         version: Option<String>,
+        /// Switch every shell by moving the shared `current` link
+        #[arg(short, long, conflicts_with = "session")]
+        global: bool,
+        /// Switch only this shell (needs the rsdk shell integration)
+        #[arg(short, long)]
+        session: bool,
     },
 
     #[command(about = "Flush internal caches")]
     Flush {},
 
+    #[command(about = "Inspect and clean up the download cache")]
+    Cache {
+        #[command(subcommand)]
+        command: CacheSubcommand,
+    },
+
+    #[command(about = "Show or change settings in the rsdk configuration file")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigSubcommand,
+    },
+
     #[command(about = "Open text mode graphical interface")]
     Tui,
 
@@ -117,14 +288,93 @@ pub enum Shell {
     Nushell,
 }
 
+impl Command {
+    /// Whether the command prints records with `--format json|tsv`.
+    pub fn has_records(&self) -> bool {
+        matches!(
+            self,
+            Command::List { .. }
+                | Command::Installed { .. }
+                | Command::Current { .. }
+                | Command::Default { .. }
+        )
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    Json,
+    Tsv,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum CacheSubcommand {
+    #[command(about = "List cached archives and API responses")]
+    List,
+
+    #[command(about = "Remove cache entries by age and/or total size")]
+    Prune {
+        /// Remove entries not used for this long (e.g. 12h, 30d, 2w)
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<Duration>,
+        /// Remove least recently used entries until the cache fits (e.g. 5G)
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_size: Option<u64>,
+        /// Never remove archives of installed tool versions
+        #[arg(long)]
+        keep_installed: bool,
+    },
+
+    #[command(about = "Remove the cached archive of a tool version")]
+    Rm { tool: String, version: String },
+}
+
+#[derive(Subcommand, Clone)]
+#[command(
+    after_help = "Keys: home, mirrors, tool_mirrors.<tool>, tool_roots.<tool>, timeout, insecure, offline, cache_ttl, max_extract_files, max_extract_size, auto_answer, auto_env, use_scope"
+)]
+pub enum ConfigSubcommand {
+    #[command(about = "List the settings in the configuration file")]
+    List,
+
+    #[command(about = "Show the value of a setting")]
+    Get { key: String },
+
+    #[command(about = "Change a setting (values are TOML, e.g. true, 30, [\"a\", \"b\"])")]
+    Set { key: String, value: String },
+
+    #[command(about = "Remove a setting, restoring its default")]
+    Unset { key: String },
+}
+
 #[derive(Subcommand, Clone)]
 pub enum EnvSubcommand {
     #[command(about = "Save current tool versions to env")]
     Init,
 
     #[command(about = "Install a tool in env or change its version")]
-    Install,
+    Install {
+        /// Only this tool, added to env if missing (default: every tool in env)
+        tool: Option<String>,
+        version: Option<String>,
+    },
+
+    #[command(about = "Add a tool to env or change its version (default: the current one)")]
+    Add {
+        tool: String,
+        version: Option<String>,
+    },
+
+    #[command(about = "Remove a tool from env")]
+    Remove { tool: String },
+
+    #[command(about = "Replace version specifiers in env with the exact installed versions")]
+    Pin,
 
     #[command(about = "Revert current tools to default version (env is untouched)")]
     Clear,
+
+    #[command(about = "Apply or revert env on directory change (run by the shell hooks)")]
+    Auto,
 }
@@ -1,10 +1,13 @@
 use color_eyre::Result;
 
+use crate::checksum::{file_digest, Algorithm, Checksum, Hasher};
 use log::debug;
 use serde::Serialize;
 use serde_derive::Deserialize;
+use std::fs;
 use std::fs::File;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug)]
 pub struct CacheEntry {
@@ -18,24 +21,190 @@ impl CacheEntry {
         self.cache_dir.join(&self.url_hash)
     }
 
+    /// Where an in-progress download is written until it completes.
+    pub fn part_path(&self) -> PathBuf {
+        self.cache_dir.join(format!("{}.part", self.url_hash))
+    }
+
     fn metadata_path(&self) -> PathBuf {
         self.cache_dir.join(format!("{}.meta", self.url_hash))
     }
 
+    /// An entry is usable when its data and metadata both exist and the data
+    /// still has the length and modification time recorded when it was
+    /// written. This is cheap enough for listings; `verify` re-hashes the data.
     pub fn is_valid(&self) -> bool {
-        self.file_path().exists() && self.metadata_path().exists()
+        let Ok(data) = fs::metadata(self.file_path()) else {
+            return false;
+        };
+        // Entries written before lengths and times were recorded have
+        // neither, and are trusted as before.
+        self.metadata_path().exists()
+            && self
+                .metadata
+                .length
+                .is_none_or(|length| data.len() == length)
+            && self
+                .metadata
+                .modified
+                .is_none_or(|modified| modified_secs(&data) == modified)
+    }
+
+    /// Check the data against the digest recorded when it was written or,
+    /// for older entries, the checksum published for it.
+    pub fn verify(&self) -> Result<()> {
+        let path = self.file_path();
+        let expected = Checksum::parse(&self.metadata.digest)
+            .or_else(|| Checksum::parse(&self.metadata.checksum));
+        if let Some(expected) = expected {
+            let actual = file_digest(&path, expected.algorithm)?;
+            expected.verify(&actual, &format!("cached {path:?}"))?;
+        }
+        Ok(())
+    }
+
+    /// Size on disk of the data, or of the partial download while the data
+    /// is not complete yet.
+    pub fn size(&self) -> u64 {
+        fs::metadata(self.file_path())
+            .or_else(|_| fs::metadata(self.part_path()))
+            .map(|m| m.len())
+            .unwrap_or(0)
+    }
+
+    /// Unix time (seconds) the entry was last used. Entries written before
+    /// usage was tracked fall back to their fetch time, then to the data
+    /// file's modification time.
+    pub fn last_used(&self) -> u64 {
+        let tracked = self.metadata.last_used.max(self.metadata.fetched_at);
+        if tracked > 0 {
+            return tracked;
+        }
+        fs::metadata(self.file_path())
+            .map(|m| modified_secs(&m))
+            .unwrap_or(0)
+    }
+
+    /// Record that the archive was just served. API responses are not
+    /// tracked: they are read at every prompt and kept fresh by `fetched_at`.
+    pub fn touch(&mut self) -> Result<()> {
+        self.metadata.last_used = unix_now();
+        self.save()
+    }
+
+    /// Time elapsed since the entry was last fetched or revalidated.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.metadata.fetched_at))
+    }
+
+    /// Read back a cached text response.
+    pub fn read_text(&self) -> Result<String> {
+        Ok(fs::read_to_string(self.file_path())?)
+    }
+
+    /// Store a text response and its metadata.
+    pub fn write_text(&mut self, text: &str) -> Result<()> {
+        debug!("caching text to {:?}", self.file_path());
+        let mut hasher = Hasher::new(DIGEST_ALGORITHM);
+        hasher.update(text.as_bytes());
+        write_atomic(&self.file_path(), text.as_bytes())?;
+        self.set_content(text.len() as u64, &hasher.finalize_hex());
+        self.save()
+    }
+
+    /// Record the length and digest (hex, [`DIGEST_ALGORITHM`]) of the data
+    /// file, along with its modification time, checked by
+    /// [`CacheEntry::is_valid`] and [`CacheEntry::verify`].
+    pub fn set_content(&mut self, length: u64, digest_hex: &str) {
+        self.metadata.length = Some(length);
+        self.metadata.modified = fs::metadata(self.file_path())
+            .ok()
+            .map(|m| modified_secs(&m));
+        self.metadata.digest = Checksum::new(DIGEST_ALGORITHM, digest_hex)
+            .map(|d| d.to_string())
+            .unwrap_or_default();
+    }
+
+    /// Delete the cached data, its metadata and any partial download.
+    pub fn remove(&self) -> Result<()> {
+        debug!("removing cache entry {:?}", self.file_path());
+        for path in [self.file_path(), self.metadata_path(), self.part_path()] {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
     }
 
     pub fn save(&self) -> Result<()> {
         debug!("saving metadata to {:?}", self.metadata_path());
-        let file = File::create(self.metadata_path())?;
-        Ok(serde_ini::to_writer(&file, &self.metadata)?)
+        let text = serde_ini::to_string(&self.metadata)?;
+        write_atomic(&self.metadata_path(), text.as_bytes())
     }
 }
 
+// `default` keeps `.meta` files written by older versions readable.
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Metadata {
+    /// URL the entry was fetched from.
+    pub url: String,
+    /// Tool and version of a cached archive; empty for API responses.
+    pub tool: String,
+    pub version: String,
     pub file_name: String,
+    /// `ETag` validator from the last response, if the server sent one.
+    pub etag: String,
+    /// `Last-Modified` validator from the last response, if the server sent one.
+    pub last_modified: String,
+    /// Unix time (seconds) of the last fetch or successful revalidation.
+    pub fetched_at: u64,
+    /// Published checksum of a downloaded archive, as `ALGORITHM:hex`.
+    pub checksum: String,
+    /// Size in bytes of the data file when it was written; `None` for
+    /// entries written before it was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
+    /// Digest of the data file when it was written, as `ALGORITHM:hex`.
+    pub digest: String,
+    /// Modification time (Unix seconds) of the data file when it was written;
+    /// `None` for entries written before it was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+    /// Unix time (seconds) the entry was last served from the cache.
+    pub last_used: u64,
+}
+
+/// Algorithm used for the integrity digest of every cache entry.
+pub const DIGEST_ALGORITHM: Algorithm = Algorithm::Sha256;
+
+/// Write `data` next to `path` and rename it into place, so readers never
+/// see a partially written file.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Modification time of a file in Unix seconds, 0 when unknown.
+fn modified_secs(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Current Unix time in seconds.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 pub struct CacheManager {
@@ -52,8 +221,58 @@ impl CacheManager {
 
     pub fn get_cache_entry(&self, url: &str) -> CacheEntry {
         let url_hash = format!("{:x}", md5::compute(url));
-        let cache_path = self.cache_dir.join(&url_hash);
-        let meta_path = cache_path.with_extension("meta");
+        let mut entry = self.load_entry(url_hash);
+        entry.metadata.url = url.to_string();
+        entry
+    }
+
+    /// Every entry that has metadata, including unfinished downloads.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.cache_dir)? {
+            let path = dir_entry?.path();
+            if path.extension().is_some_and(|ext| ext == "meta") {
+                if let Some(url_hash) = path.file_stem().and_then(|s| s.to_str()) {
+                    entries.push(self.load_entry(url_hash.to_string()));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Remove entries not protected by `keep`: first those unused for longer
+    /// than `older_than`, then the least recently used ones until the cache
+    /// fits in `max_size` bytes. Returns the number of entries removed and
+    /// the bytes freed.
+    pub fn prune(
+        &self,
+        older_than: Option<Duration>,
+        max_size: Option<u64>,
+        keep: impl Fn(&CacheEntry) -> bool,
+    ) -> Result<(usize, u64)> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|e| e.last_used());
+
+        let now = unix_now();
+        let mut total: u64 = entries.iter().map(|e| e.size()).sum();
+        let (mut removed, mut freed) = (0, 0);
+        for entry in entries {
+            let too_old =
+                older_than.is_some_and(|age| now.saturating_sub(entry.last_used()) > age.as_secs());
+            let too_big = max_size.is_some_and(|max| total > max);
+            if (too_old || too_big) && !keep(&entry) {
+                let size = entry.size();
+                entry.remove()?;
+                total = total.saturating_sub(size);
+                freed += size;
+                removed += 1;
+            }
+        }
+        Ok((removed, freed))
+    }
+
+    fn load_entry(&self, url_hash: String) -> CacheEntry {
+        let meta_path = self.cache_dir.join(format!("{url_hash}.meta"));
         let metadata: Metadata = meta_path
             .exists()
             .then_some(meta_path)
@@ -68,3 +287,158 @@ impl CacheManager {
         }
     }
 }
+
+/// Parse a byte size such as `500M`, `500MB`, `5G` or `1.5GiB` (binary
+/// units either way).
+pub fn parse_size(s: &str) -> std::result::Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{s}', expected e.g. 500M or 5G"))?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let prefix = unit
+        .strip_suffix("IB")
+        .or_else(|| unit.strip_suffix('B'))
+        .unwrap_or(&unit);
+    let multiplier: u64 = match prefix {
+        "" | "B" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => {
+            return Err(format!(
+                "invalid size unit in '{s}', expected K, M, G or T (optionally with B or iB)"
+            ))
+        }
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse an age such as `45m`, `12h`, `30d` or `2w`.
+pub fn parse_age(s: &str) -> std::result::Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid age '{s}', expected e.g. 12h or 30d"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "" => return Err(format!("missing unit in age '{s}', e.g. {s}d")),
+        _ => {
+            return Err(format!(
+                "invalid age unit in '{s}', expected s, m, h, d or w"
+            ))
+        }
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("age '{s}' is too large"))
+}
+
+/// Human-readable byte size (`195.2 MiB`).
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Human-readable time since a Unix timestamp (`3 days ago`).
+pub fn format_age(unix_secs: u64) -> String {
+    if unix_secs == 0 {
+        return "unknown".to_string();
+    }
+    let secs = unix_now().saturating_sub(unix_secs);
+    let (n, unit) = match secs {
+        s if s < 60 => return "just now".to_string(),
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 24 * 60 * 60 => (s / (60 * 60), "hour"),
+        s => (s / (24 * 60 * 60), "day"),
+    };
+    format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_metadata_still_loads() {
+        // `.meta` files written before validators were tracked only have a
+        // file name; they must load with the new fields defaulted.
+        let meta: Metadata = serde_ini::from_str("file_name=jdk.tar.gz\r\n").unwrap();
+        assert_eq!(meta.file_name, "jdk.tar.gz");
+        assert!(meta.etag.is_empty());
+        assert_eq!(meta.fetched_at, 0);
+    }
+
+    #[test]
+    fn damaged_entries_fail_the_checks() {
+        let dir = std::env::temp_dir().join(format!("rsdk-cachetest-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let cache = CacheManager::new(&dir);
+
+        let mut entry = cache.get_cache_entry("https://example.com/candidates/all");
+        entry.write_text("java,maven,gradle").unwrap();
+        let entry = cache.get_cache_entry("https://example.com/candidates/all");
+        assert!(entry.is_valid());
+        assert!(entry.verify().is_ok());
+        assert_eq!(entry.read_text().unwrap(), "java,maven,gradle");
+
+        // Same length and time: only the digest tells, and nothing is evicted.
+        let modified = fs::metadata(entry.file_path()).unwrap().modified().unwrap();
+        fs::write(entry.file_path(), "JAVA,maven,gradle").unwrap();
+        File::options()
+            .write(true)
+            .open(entry.file_path())
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert!(entry.is_valid());
+        assert!(entry.verify().is_err());
+        assert!(entry.file_path().exists());
+
+        fs::write(entry.file_path(), "java,mav").unwrap();
+        assert!(!entry.is_valid());
+        assert!(entry.metadata_path().exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_sizes_and_ages() {
+        assert_eq!(parse_size("500M"), Ok(500 << 20));
+        assert_eq!(parse_size("1.5GiB"), Ok(3 << 29));
+        assert_eq!(parse_size("500MB"), Ok(500 << 20));
+        assert_eq!(parse_size("5gb"), Ok(5 << 30));
+        assert_eq!(parse_size("2KiB"), Ok(2048));
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("1024B"), Ok(1024));
+        assert!(parse_size("5X").is_err());
+        assert!(parse_size("5XB").is_err());
+        assert_eq!(parse_age("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_age("30d"), Ok(Duration::from_secs(30 * 86400)));
+        assert!(parse_age("soon").is_err());
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("18446744073709551615w").is_err());
+        assert_eq!(format_size(3 << 29), "1.5 GiB");
+    }
+}
@@ -0,0 +1,217 @@
+//! Archive checksums: parsing the ones SDKMAN publishes and verifying
+//! downloaded files against them.
+
+use color_eyre::Result;
+use eyre::bail;
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+/// Prefix of the broker response headers carrying archive checksums, e.g.
+/// `X-Sdkman-Checksum-SHA-256: <hex>` (same headers the SDKMAN CLI checks).
+pub const CHECKSUM_HEADER_PREFIX: &str = "x-sdkman-checksum-";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    /// Parse an algorithm name as it appears in SDKMAN headers (`SHA-256`,
+    /// `sha256`, `MD5`...). Returns `None` for unsupported algorithms.
+    pub fn parse(name: &str) -> Option<Algorithm> {
+        match name.to_ascii_uppercase().replace('-', "").as_str() {
+            "MD5" => Some(Algorithm::Md5),
+            "SHA256" => Some(Algorithm::Sha256),
+            "SHA512" => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha512 => "SHA-512",
+        }
+    }
+
+    /// Preference when several checksums are available: strongest first.
+    pub(crate) fn strength(&self) -> u8 {
+        match self {
+            Algorithm::Md5 => 0,
+            Algorithm::Sha256 => 1,
+            Algorithm::Sha512 => 2,
+        }
+    }
+}
+
+/// An expected digest, as lowercase hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    pub hex: String,
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm.name(), self.hex)
+    }
+}
+
+impl Checksum {
+    pub fn new(algorithm: Algorithm, hex: &str) -> Option<Checksum> {
+        let hex = hex.trim().to_ascii_lowercase();
+        let expected_len = match algorithm {
+            Algorithm::Md5 => 32,
+            Algorithm::Sha256 => 64,
+            Algorithm::Sha512 => 128,
+        };
+        (hex.len() == expected_len && hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .then_some(Checksum { algorithm, hex })
+    }
+
+    /// Parse the `ALGORITHM:hex` form produced by `Display` (as stored in
+    /// cache metadata).
+    pub fn parse(s: &str) -> Option<Checksum> {
+        let (algorithm, hex) = s.split_once(':')?;
+        Checksum::new(Algorithm::parse(algorithm)?, hex)
+    }
+
+    /// Pick the strongest checksum among `(header name, value)` pairs,
+    /// ignoring headers that are not SDKMAN checksums.
+    pub fn from_headers<'a>(
+        headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Option<Checksum> {
+        headers
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let algorithm = name
+                    .to_ascii_lowercase()
+                    .strip_prefix(CHECKSUM_HEADER_PREFIX)
+                    .and_then(Algorithm::parse)?;
+                Checksum::new(algorithm, value)
+            })
+            .max_by_key(|c| c.algorithm.strength())
+    }
+
+    /// Parse a `sha256sum`-style sidecar file (`<hex>  <file name>`).
+    pub fn from_sidecar(algorithm: Algorithm, text: &str) -> Option<Checksum> {
+        Checksum::new(algorithm, text.split_whitespace().next()?)
+    }
+
+    /// Fail unless `actual` (hex) matches this checksum.
+    pub fn verify(&self, actual: &str, what: &str) -> Result<()> {
+        if !self.hex.eq_ignore_ascii_case(actual) {
+            bail!(
+                "{} checksum mismatch for {what}: expected {}, got {actual}",
+                self.algorithm.name(),
+                self.hex
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Incremental digest over one of the supported algorithms.
+pub enum Hasher {
+    Md5(md5::Context),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    pub fn new(algorithm: Algorithm) -> Hasher {
+        match algorithm {
+            Algorithm::Md5 => Hasher::Md5(md5::Context::new()),
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(ctx) => ctx.consume(data),
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+        }
+    }
+
+    /// Feed everything `reader` yields into the digest.
+    pub fn update_from(&mut self, mut reader: impl Read) -> io::Result<()> {
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buffer)?;
+            if n == 0 {
+                return Ok(());
+            }
+            self.update(&buffer[..n]);
+        }
+    }
+
+    pub fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Md5(ctx) => format!("{:x}", ctx.finalize()),
+            Hasher::Sha256(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha512(h) => format!("{:x}", h.finalize()),
+        }
+    }
+}
+
+/// Hash a whole file.
+pub fn file_digest(path: &Path, algorithm: Algorithm) -> Result<String> {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update_from(File::open(path)?)?;
+    Ok(hasher.finalize_hex())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn picks_strongest_sdkman_header() {
+        let headers = [
+            ("Content-Type", "application/octet-stream"),
+            ("X-Sdkman-Checksum-MD5", "5d41402abc4b2a76b9719d911017c592"),
+            ("X-Sdkman-Checksum-SHA-256", HELLO_SHA256),
+        ];
+        let checksum = Checksum::from_headers(headers).unwrap();
+        assert_eq!(checksum.algorithm, Algorithm::Sha256);
+        assert_eq!(checksum.hex, HELLO_SHA256);
+    }
+
+    #[test]
+    fn rejects_malformed_checksums() {
+        assert!(Checksum::new(Algorithm::Sha256, "abc").is_none());
+        assert!(Checksum::from_headers([("X-Sdkman-Checksum-CRC32", "abcd1234")]).is_none());
+    }
+
+    #[test]
+    fn display_roundtrips_through_parse() {
+        let checksum = Checksum::new(Algorithm::Sha256, HELLO_SHA256).unwrap();
+        assert_eq!(Checksum::parse(&checksum.to_string()), Some(checksum));
+    }
+
+    #[test]
+    fn hashes_and_verifies() {
+        for (algorithm, expected) in [
+            (Algorithm::Md5, "5d41402abc4b2a76b9719d911017c592"),
+            (Algorithm::Sha256, HELLO_SHA256),
+        ] {
+            let mut hasher = Hasher::new(algorithm);
+            hasher.update(b"hello");
+            let checksum = Checksum::new(algorithm, expected).unwrap();
+            checksum.verify(&hasher.finalize_hex(), "hello").unwrap();
+        }
+        let checksum = Checksum::from_sidecar(Algorithm::Sha256, &format!("{HELLO_SHA256}  x"));
+        assert!(checksum.unwrap().verify("00", "x").is_err());
+    }
+}
@@ -0,0 +1,328 @@
+//! Persistent settings, read once at startup from `~/.rsdk/config.toml` (or
+//! the file named by `RSDK_CONFIG`). Command-line flags and environment
+//! variables take precedence over these, see the accessors in `args`.
+
+use crate::archive::RootStrategy;
+use crate::args;
+use crate::cache::parse_size;
+use color_eyre::Result;
+use directories::UserDirs;
+use eyre::{bail, eyre, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Overrides the location of the configuration file.
+pub const RSDK_CONFIG: &str = "RSDK_CONFIG";
+
+/// Contents of `config.toml`. Every key is optional; missing keys keep the
+/// built-in behaviour.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where tools, symlinks and the cache live (default `~/.rsdk`), unless
+    /// `--home` or `RSDK_HOME` is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub home: Option<PathBuf>,
+    /// SDKMAN API base URLs, tried in order until one answers (e.g. an
+    /// internal proxy first, then `https://api.sdkman.io/2`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+    /// Per-tool mirror lists, used instead of `mirrors` for that tool's
+    /// versions, defaults and downloads.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tool_mirrors: BTreeMap<String, Vec<String>>,
+    /// Per-tool way to find the install dir in downloaded archives.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tool_roots: BTreeMap<String, RootStrategy>,
+    /// HTTP request timeout, in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insecure: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline: Option<bool>,
+    /// Seconds cached API responses are used before revalidating them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_ttl: Option<u64>,
+    /// Most files one archive may extract.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_extract_files: Option<u64>,
+    /// Most bytes one archive may extract, as bytes or like `8G`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_extract_size: Option<ByteSize>,
+    /// Answer every confirmation prompt with this instead of asking.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_answer: Option<Answer>,
+    /// Apply the nearest `.sdkmanrc` whenever the shell changes directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_env: Option<bool>,
+    /// Whether `use` changes the version for the calling shell only
+    /// (`session`, the default) or for every shell (`global`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_scope: Option<UseScope>,
+}
+
+/// A byte size, written as a number of bytes or like `500M` or `8G`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "toml::Value", into = "u64")]
+pub struct ByteSize(pub u64);
+
+impl TryFrom<toml::Value> for ByteSize {
+    type Error = String;
+
+    fn try_from(value: toml::Value) -> std::result::Result<Self, Self::Error> {
+        match value {
+            toml::Value::Integer(n) if n >= 0 => Ok(ByteSize(n as u64)),
+            toml::Value::String(s) => parse_size(&s).map(ByteSize),
+            other => Err(format!("invalid size {other}, expected e.g. 500M or 8G")),
+        }
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> u64 {
+        size.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Answer {
+    Yes,
+    No,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UseScope {
+    Session,
+    Global,
+}
+
+pub static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The loaded configuration, or the defaults when none was loaded (e.g. when
+/// the library is used from integration tests).
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// `$RSDK_CONFIG` if set, else `config.toml` in the root given with `--home`
+/// or `RSDK_HOME`, else `~/.rsdk/config.toml`. The `home` key itself can't
+/// move the file, since it is read from it.
+pub fn config_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(RSDK_CONFIG) {
+        return PathBuf::from(path);
+    }
+    if let Some(home) = args::home() {
+        return home.join("config.toml");
+    }
+    let user_dirs = UserDirs::new().expect("Failed to get user directories");
+    user_dirs.home_dir().join(".rsdk").join("config.toml")
+}
+
+impl Config {
+    /// Load `path`, treating a missing file as an empty configuration.
+    pub fn load(path: &Path) -> Result<Config> {
+        let table = read_table(path)?;
+        Config::from_table(table).wrap_err_with(|| format!("invalid configuration file {path:?}"))
+    }
+
+    fn from_table(table: toml::Table) -> Result<Config> {
+        Ok(Config::deserialize(toml::Value::Table(table))?)
+    }
+}
+
+/// Every `key = value` pair set in the file at `path`, with nested tables
+/// flattened to dotted keys (`tool_mirrors.java`).
+pub fn list(path: &Path) -> Result<Vec<(String, String)>> {
+    let mut entries = vec![];
+    flatten("", &toml::Value::Table(read_table(path)?), &mut entries);
+    Ok(entries)
+}
+
+/// The value of a dotted `key` in the file at `path`, if set.
+pub fn get(path: &Path, key: &str) -> Result<Option<String>> {
+    check_key(key)?;
+    let table = read_table(path)?;
+    let mut value = None;
+    let mut current = Some(&table);
+    for part in key.split('.') {
+        value = current.and_then(|t| t.get(part));
+        current = value.and_then(|v| v.as_table());
+    }
+    Ok(value.map(format_value))
+}
+
+/// Set `key` to `value` in the file at `path`. `value` is read as a TOML value
+/// (`true`, `30`, `["a", "b"]`), anything else is taken as a plain string.
+/// The file is only written if the result is a valid configuration.
+pub fn set(path: &Path, key: &str, value: &str) -> Result<()> {
+    check_key(key)?;
+    let mut value = parse_value(value);
+    let (parents, leaf) = split_key(key);
+    // A single mirror is a one-element list.
+    if (leaf == "mirrors" || parents == ["tool_mirrors"]) && value.is_str() {
+        value = toml::Value::Array(vec![value]);
+    }
+    let mut table = read_table(path)?;
+    let mut current = &mut table;
+    for part in parents {
+        current = current
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| eyre!("'{part}' is not a table"))?;
+    }
+    current.insert(leaf.to_string(), value);
+    save(path, table)
+}
+
+/// Remove `key` from the file at `path`. Returns whether it was set.
+pub fn unset(path: &Path, key: &str) -> Result<bool> {
+    check_key(key)?;
+    let mut table = read_table(path)?;
+    let (parents, leaf) = split_key(key);
+    let mut current = Some(&mut table);
+    for part in parents {
+        current = current
+            .and_then(|t| t.get_mut(part))
+            .and_then(|v| v.as_table_mut());
+    }
+    let removed = current.and_then(|t| t.remove(leaf)).is_some();
+    if removed {
+        save(path, table)?;
+    }
+    Ok(removed)
+}
+
+/// Keys accepted in `config.toml`, as listed by `rsdk config --help`.
+pub const KEYS: &[&str] = &[
+    "home",
+    "mirrors",
+    "tool_mirrors.<tool>",
+    "tool_roots.<tool>",
+    "timeout",
+    "insecure",
+    "offline",
+    "cache_ttl",
+    "max_extract_files",
+    "max_extract_size",
+    "auto_answer",
+    "auto_env",
+    "use_scope",
+];
+
+fn check_key(key: &str) -> Result<()> {
+    let (parents, leaf) = split_key(key);
+    let known = match parents.as_slice() {
+        [] => KEYS.contains(&leaf),
+        ["tool_mirrors" | "tool_roots"] => !leaf.is_empty(),
+        _ => false,
+    };
+    if !known {
+        bail!(
+            "unknown configuration key '{key}' (known keys: {})",
+            KEYS.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn split_key(key: &str) -> (Vec<&str>, &str) {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let leaf = parts.pop().unwrap_or_default();
+    (parts, leaf)
+}
+
+fn parse_value(value: &str) -> toml::Value {
+    format!("value = {value}")
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut t| t.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+fn format_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn flatten(prefix: &str, value: &toml::Value, entries: &mut Vec<(String, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, entries);
+            }
+        }
+        value => entries.push((prefix.to_string(), format_value(value))),
+    }
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    let text = fs::read_to_string(path)?;
+    text.parse()
+        .wrap_err_with(|| format!("invalid configuration file {path:?}"))
+}
+
+fn save(path: &Path, table: toml::Table) -> Result<()> {
+    Config::from_table(table.clone()).wrap_err("invalid configuration value")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, toml::to_string(&table)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_get_unset_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("rsdk-config-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("config.toml");
+
+        set(&path, "timeout", "10").unwrap();
+        set(&path, "auto_answer", "yes").unwrap();
+        set(&path, "tool_mirrors.java", "https://mirror.example.com/2").unwrap();
+        assert_eq!(get(&path, "timeout").unwrap().as_deref(), Some("10"));
+        assert_eq!(
+            get(&path, "tool_mirrors.java").unwrap().as_deref(),
+            Some(r#"["https://mirror.example.com/2"]"#)
+        );
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.timeout, Some(10));
+        assert_eq!(config.auto_answer, Some(Answer::Yes));
+
+        assert!(unset(&path, "timeout").unwrap());
+        assert!(!unset(&path, "timeout").unwrap());
+        assert_eq!(list(&path).unwrap().len(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_values() {
+        let dir = std::env::temp_dir().join(format!("rsdk-config-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("config.toml");
+
+        assert!(set(&path, "colour", "blue").is_err());
+        assert!(set(&path, "timeout", "soon").is_err());
+        assert!(!path.exists());
+    }
+}
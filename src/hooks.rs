@@ -0,0 +1,206 @@
+//! SDKMAN post-install hooks, from `/hooks/post/{tool}/{version}/{platform}`.
+//!
+//! SDKMAN runs these bash scripts after downloading a tool to turn the archive
+//! into the layout it installs (`<version>/bin/...`). rsdk never runs them: it
+//! reads what the script does to the layout and does the same natively.
+//! Hooks that only repackage a tarball as a zip need nothing, since tarballs
+//! are extracted directly.
+
+use crate::archive::RootStrategy;
+use crate::sdkman_client::{self, SdkManClient};
+use log::{debug, warn};
+use std::fs;
+use std::path::Path;
+
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+/// How the downloaded file becomes the install dir.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layout {
+    /// An archive holding the tool dir (SDKMAN's `default` hook, and those
+    /// repackaging tarballs).
+    Archive,
+    /// A macOS bundle: the tool lives in `<bundle>/Contents/Home`.
+    MacosBundle,
+    /// The archive's tool dir is nested in wrapper dirs, to be stripped down
+    /// to the first one holding `bin/`.
+    Nested,
+    /// A bare executable, installed as `bin/<name>`.
+    Binary { name: String },
+}
+
+/// The layout of `tool` `version` on this platform, `Archive` when there is
+/// no hook (or none cached while offline) or it can't be fetched.
+pub fn post_install_layout(
+    api: &SdkManClient,
+    tool: &str,
+    version: &str,
+) -> color_eyre::Result<Layout> {
+    match api.get_post_install(tool, version) {
+        Ok(script) => Ok(parse(tool, &script)),
+        Err(e) if sdkman_client::is_missing(&e) => {
+            debug!("no post-install hook for {tool} {version}: {e:#}");
+            Ok(Layout::Archive)
+        }
+        Err(e) => {
+            warn!("can't fetch the post-install hook of {tool} {version}, installing as is: {e:#}");
+            Ok(Layout::Archive)
+        }
+    }
+}
+
+/// Read the layout a hook script produces, from its commands only: comments
+/// and `echo`ed text don't count.
+pub fn parse(tool: &str, script: &str) -> Layout {
+    let name = script
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("#Post Hook:"))
+        .map_or("unnamed", str::trim);
+    let commands: Vec<&str> = script
+        .lines()
+        .map(|line| strip_comment(line).trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let moves = |c: &&str| is_command(c, "mv") || is_command(c, "cp");
+
+    let layout = if commands
+        .iter()
+        .any(|c| moves(c) && c.contains("Contents/Home"))
+    {
+        Layout::MacosBundle
+    } else if let Some(name) = binary_name(tool, &commands) {
+        Layout::Binary { name }
+    } else if commands
+        .iter()
+        .any(|c| is_command(c, "mv") && (c.contains("*/*") || c.contains("/*/")))
+    {
+        Layout::Nested
+    } else {
+        Layout::Archive
+    };
+    debug!("post-install hook '{name}' gives layout {layout:?}");
+    layout
+}
+
+/// `line` without its comment: from a `#` starting a word outside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut word_start = true;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') if word_start => return &line[..i],
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+        word_start = c.is_whitespace();
+    }
+    line
+}
+
+/// The executable name when the hook moves the download itself into a
+/// `bin/` dir, rather than extracting it.
+fn binary_name(tool: &str, commands: &[&str]) -> Option<String> {
+    let placed = commands.iter().find(|c| {
+        (is_command(c, "mv") || is_command(c, "cp"))
+            && c.contains("$binary_input")
+            && c.contains("/bin/")
+    })?;
+    let target = placed.rsplit("/bin/").next()?;
+    let name = target.trim_matches(|c: char| c == '"' || c == '\'' || c.is_whitespace());
+    Some(match name {
+        "" | "$candidate" | "${candidate}" => tool.to_string(),
+        name if name.contains('$') => tool.to_string(),
+        name => name.to_string(),
+    })
+}
+
+/// Whether `line` runs `command`, possibly through `/usr/bin/env`.
+fn is_command(line: &str, command: &str) -> bool {
+    let line = line.strip_prefix("/usr/bin/env ").unwrap_or(line);
+    line.split_whitespace().next() == Some(command)
+}
+
+impl Layout {
+    /// How to find the install dir in the extracted archive. macOS bundles
+    /// are recognised by `RootStrategy::Auto` already.
+    pub fn root_strategy(&self) -> RootStrategy {
+        match self {
+            Layout::Nested => RootStrategy::Bin,
+            Layout::Archive | Layout::MacosBundle | Layout::Binary { .. } => RootStrategy::Auto,
+        }
+    }
+}
+
+/// Install the bare executable `file` as `<dir>/bin/<name>`.
+pub fn place_binary(file: &Path, dir: &Path, name: &str) -> color_eyre::Result<()> {
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin)?;
+    let target = bin.join(name);
+    debug!("copying {file:?} to {target:?}");
+    fs::copy(file, &target)?;
+    #[cfg(unix)]
+    fs::set_permissions(&target, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_layout_from_hook_scripts() {
+        let default = r#"#!/bin/bash
+#Post Hook: default
+function __sdkman_post_installation_hook {
+    __sdkman_echo_debug "No Post-installation hook found for $candidate $version."
+    mv -f "$binary_input" "$zip_output"
+}"#;
+        assert_eq!(parse("maven", default), Layout::Archive);
+
+        let macos = r#"#Post Hook: osx-java-tarball
+function __sdkman_post_installation_hook {
+    /usr/bin/env tar zxf "$binary_input" -C "$work_dir"
+    cd "$work_dir"
+    /usr/bin/env mv -f */Contents/Home "$candidate-$version"
+    /usr/bin/env zip -qyr "$zip_output" "$candidate-$version"
+}"#;
+        assert_eq!(parse("java", macos), Layout::MacosBundle);
+
+        let binary = r#"#Post Hook: unix-binary
+function __sdkman_post_installation_hook {
+    mkdir -p "$work_dir/$candidate-$version/bin"
+    mv "$binary_input" "$work_dir/$candidate-$version/bin/$candidate"
+    chmod +x "$work_dir/$candidate-$version/bin/$candidate"
+}"#;
+        assert_eq!(
+            parse("kubectl", binary),
+            Layout::Binary {
+                name: "kubectl".to_string()
+            }
+        );
+
+        let nested = r#"#Post Hook: nested
+function __sdkman_post_installation_hook {
+    unzip -qo "$binary_input" -d "$work_dir"
+    mv "$work_dir"/*/* "$work_dir/$candidate-$version"
+}"#;
+        assert_eq!(parse("spark", nested), Layout::Nested);
+    }
+
+    #[test]
+    fn ignores_comments_and_echoed_text() {
+        let commented = r#"#Post Hook: default
+function __sdkman_post_installation_hook {
+    # Unlike the osx hook, no need to mv */Contents/Home here
+    echo "moving to $work_dir/bin/ and */* is not needed"
+    mv -f "$binary_input" "$zip_output" # not into */Contents/Home
+}"#;
+        assert_eq!(parse("maven", commented), Layout::Archive);
+
+        assert_eq!(strip_comment("mv a b # c"), "mv a b ");
+        assert_eq!(strip_comment("mv \"a #1\" b"), "mv \"a #1\" b");
+        assert_eq!(strip_comment("echo ${#array}"), "echo ${#array}");
+    }
+}
@@ -1,86 +1,198 @@
 use color_eyre::Result;
-use eyre::{Context, ContextCompat};
-use log::debug;
-use reqwest::blocking::Client;
-use reqwest::header;
+use eyre::{bail, Context, ContextCompat};
+use log::{debug, warn};
+use reqwest::blocking::{Client, ClientBuilder, Response};
+use reqwest::redirect::Policy;
+use reqwest::{header, StatusCode};
 
 use crate::args;
-use crate::cache::{CacheEntry, CacheManager};
+use crate::cache::{unix_now, CacheEntry, CacheManager, DIGEST_ALGORITHM};
+use crate::checksum::{Algorithm, Checksum, Hasher};
 use crate::http_utils::{
     extract_filename_from_disposition, initialize_progress_bar, read_proxy_from_env,
 };
-use std::fs::File;
-use std::io::{Read, Write};
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
+/// Upper bound on redirects followed while probing a download.
+const MAX_REDIRECTS: usize = 10;
+
+/// Reads a download as it arrives, given the file name the server sent, e.g.
+/// to extract it while it downloads. Whatever it leaves unread is still
+/// downloaded, and the whole file is verified once it is complete.
+pub type Consumer<'a> = &'a mut dyn FnMut(&mut dyn Read, &str) -> Result<()>;
+
 pub struct CachedHttpClient {
     cache: CacheManager,
     client: Client,
+    /// Same settings as `client` but never follows redirects, so `probe`
+    /// can see the headers of every hop.
+    no_redirect_client: Client,
+    /// Serve everything from the cache, see `args::offline`.
+    offline: bool,
+    /// See `args::cache_ttl`.
+    cache_ttl: Duration,
 }
 
 impl CachedHttpClient {
     pub fn new(cache_dir: &Path) -> Self {
-        let mut client = Client::builder().timeout(Duration::from_secs(30));
-
-        if let Some(proxy) = read_proxy_from_env() {
-            client = client.proxy(proxy);
-        }
-
-        if args::insecure() {
-            client = client.danger_accept_invalid_certs(true)
-        }
-
-        let client = client.build().expect("Failed to build reqwest client");
+        let client = client_builder()
+            .build()
+            .expect("Failed to build reqwest client");
+        let no_redirect_client = client_builder()
+            .redirect(Policy::none())
+            .build()
+            .expect("Failed to build reqwest client");
         Self {
             cache: CacheManager::new(cache_dir),
             client,
+            no_redirect_client,
+            offline: args::offline(),
+            cache_ttl: args::cache_ttl(),
         }
     }
 
+    /// Fetch a text resource through the cache. Entries younger than the
+    /// cache TTL are returned as-is; older ones are revalidated with a
+    /// conditional request (`If-None-Match` / `If-Modified-Since`). Network
+    /// errors are returned so another mirror can be tried, see `get_stale_text`.
     pub fn get_text(&self, url: &str) -> Result<String> {
+        let mut entry = self.cache.get_cache_entry(url);
+        if self.offline {
+            debug!("offline, reading text for {url} from cache");
+            if !entry.is_valid() {
+                return Err(not_cached(url));
+            }
+            return serve_cached_text(&entry);
+        }
+
+        let cached = entry.is_valid();
+        if cached && entry.age() < self.cache_ttl {
+            debug!("using cached text for {url}");
+            return serve_cached_text(&entry);
+        }
+
         debug!("getting text for {url}");
-        let response = self.client.get(url).send()?;
+        let mut request = self.client.get(url);
+        if cached {
+            if !entry.metadata.etag.is_empty() {
+                request = request.header(header::IF_NONE_MATCH, &entry.metadata.etag);
+            }
+            if !entry.metadata.last_modified.is_empty() {
+                request = request.header(header::IF_MODIFIED_SINCE, &entry.metadata.last_modified);
+            }
+        }
+
+        let response = request.send()?;
+
+        if cached && response.status() == StatusCode::NOT_MODIFIED {
+            debug!("{url} not modified");
+            entry.metadata.fetched_at = unix_now();
+            entry.save()?;
+            return serve_cached_text(&entry);
+        }
+
+        let response = response.error_for_status()?;
+        let header_value = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+                .to_string()
+        };
+        entry.metadata.etag = header_value(header::ETAG);
+        entry.metadata.last_modified = header_value(header::LAST_MODIFIED);
+        entry.metadata.fetched_at = unix_now();
+        entry.metadata.last_used = entry.metadata.fetched_at;
         let content = response.text()?;
+        entry.write_text(&content)?;
         Ok(content)
     }
 
-    pub fn get_cached_file(&self, url: &str) -> Result<CacheEntry> {
+    /// The cached copy of the text at `url`, however old, for when every
+    /// mirror failed.
+    pub fn get_stale_text(&self, url: &str) -> Option<String> {
+        let entry = self.cache.get_cache_entry(url);
+        if !entry.is_valid() {
+            return None;
+        }
+        warn!("failed to fetch {url}, using stale cached copy");
+        serve_cached_text(&entry).ok()
+    }
+
+    /// Get the archive at `url` through the cache. `tool` and `version` are
+    /// recorded in the entry's metadata for `rsdk cache` commands. A fresh
+    /// download is also fed to `consume`, if given; cached and resumed ones
+    /// are not.
+    pub fn get_cached_file(
+        &self,
+        url: &str,
+        tool: &str,
+        version: &str,
+        consume: Option<Consumer>,
+    ) -> Result<CacheEntry> {
         debug!("Getting file for {url}");
         let mut entry = self.cache.get_cache_entry(url);
+        entry.metadata.tool = tool.to_string();
+        entry.metadata.version = version.to_string();
 
         if !entry.is_valid() {
+            if self.offline {
+                return Err(not_cached(url));
+            }
             debug!("Downloading file");
-            let name = self.download_to_file(url, &entry.file_path())?;
-            entry.metadata.file_name = name;
-            entry.save()?;
+            let remote = self.probe(url)?;
+            let pb = initialize_progress_bar(remote.total_size, &remote.file_name);
+            self.download(
+                &remote,
+                &mut entry,
+                &mut |bytes, _| pb.set_position(bytes),
+                &AtomicBool::new(false),
+                consume,
+            )?;
+            pb.finish_with_message("Download completed");
         } else {
             debug!("File found in cache");
+            verify_cached(&entry)?;
+            entry.touch()?;
         };
 
         Ok(entry)
     }
 
     /// Monitored variant: reports `(bytes_downloaded, total_size)` via the
-    /// callback after each chunk, and aborts (cleaning up the partial file)
-    /// when `cancel` is set. Used by the TUI for its progress modal.
+    /// callback after each chunk, and aborts when `cancel` is set, keeping
+    /// the partial file so the next attempt resumes it. Used by the TUI for
+    /// its progress modal.
     pub fn get_cached_file_monitored(
         &self,
         url: &str,
+        tool: &str,
+        version: &str,
         on_progress: &mut dyn FnMut(u64, u64),
         cancel: &AtomicBool,
+        consume: Option<Consumer>,
     ) -> Result<CacheEntry> {
         debug!("Getting (monitored) file for {url}");
         let mut entry = self.cache.get_cache_entry(url);
+        entry.metadata.tool = tool.to_string();
+        entry.metadata.version = version.to_string();
 
         if !entry.is_valid() {
-            let name =
-                self.download_to_file_monitored(url, &entry.file_path(), on_progress, cancel)?;
-            entry.metadata.file_name = name;
-            entry.save()?;
+            if self.offline {
+                return Err(not_cached(url));
+            }
+            let remote = self.probe(url)?;
+            self.download(&remote, &mut entry, on_progress, cancel, consume)?;
         } else {
             debug!("File found in cache");
+            verify_cached(&entry)?;
+            entry.touch()?;
             let len = std::fs::metadata(entry.file_path())?.len();
             on_progress(len, len);
         }
@@ -88,96 +200,631 @@ impl CachedHttpClient {
         Ok(entry)
     }
 
-    fn download_to_file(&self, url: &str, file_path: &Path) -> Result<String> {
-        let head_response = self
-            .client
-            .head(url)
-            .send()
-            .context("Failed to send HEAD request")?;
-        let headers = head_response.headers().clone();
-        let total_size = headers
-            .get(header::CONTENT_LENGTH)
-            .and_then(|len| len.to_str().ok()?.parse::<u64>().ok())
-            .context("Failed to get content length")?;
-
-        let file_name = headers
-            .get(header::CONTENT_DISPOSITION)
-            .and_then(|value| extract_filename_from_disposition(value.to_str().ok()?))
-            .unwrap_or("")
-            .to_string();
-
-        let pb = initialize_progress_bar(total_size, &file_name);
-
-        let mut response = self
-            .client
-            .get(url)
-            .send()
-            .context("Failed to send GET request")?;
-        debug!("HTTP response status {}", response.status());
-        if response.status() == 304 {
-            return Ok(file_name);
-        }
+    /// Follow the redirect chain of `url` with HEAD requests, collecting the
+    /// size, file name, validators and checksum of the archive. SDKMAN's
+    /// broker puts the `X-Sdkman-Checksum-*` headers on its redirect response
+    /// rather than on the vendor's final one, hence the manual redirect
+    /// handling. When no hop carries a checksum, a `<url>.sha256` sidecar is
+    /// tried instead.
+    fn probe(&self, url: &str) -> Result<Remote> {
+        let mut url = reqwest::Url::parse(url)?;
+        let mut checksums = Vec::new();
+        let mut file_name = String::new();
+
+        for _ in 0..MAX_REDIRECTS {
+            let response = self
+                .no_redirect_client
+                .head(url.clone())
+                .send()
+                .context("Failed to send HEAD request")?;
+            let headers = response.headers();
+            if let Some(checksum) = Checksum::from_headers(
+                headers
+                    .iter()
+                    .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+            ) {
+                checksums.push(checksum);
+            }
+            if let Some(name) = headers
+                .get(header::CONTENT_DISPOSITION)
+                .and_then(|value| extract_filename_from_disposition(value.to_str().ok()?))
+            {
+                file_name = name.to_string();
+            }
+
+            if response.status().is_redirection() {
+                let location = headers
+                    .get(header::LOCATION)
+                    .and_then(|l| l.to_str().ok())
+                    .context("redirect without a Location header")?;
+                url = url.join(location)?;
+                debug!("redirected to {url}");
+                continue;
+            }
+
+            let response = response.error_for_status()?;
+            let header_value = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("")
+                    .to_string()
+            };
+            let total_size = header_value(header::CONTENT_LENGTH)
+                .parse::<u64>()
+                .ok()
+                .context("Failed to get content length")?;
+            let etag = header_value(header::ETAG);
+            let last_modified = header_value(header::LAST_MODIFIED);
+            let accepts_ranges = header_value(header::ACCEPT_RANGES) != "none";
 
-        let mut cache_file = File::create(file_path).context("Failed to create cache file")?;
-        let mut buffer = [0; 8192];
-        while let Ok(bytes_read) = response.read(&mut buffer) {
-            if bytes_read == 0 {
-                break;
+            let checksum = checksums
+                .into_iter()
+                .max_by_key(|c| c.algorithm.strength())
+                .or_else(|| self.sidecar_checksum(&url));
+            match &checksum {
+                Some(checksum) => debug!("expecting {checksum}"),
+                None => warn!("no checksum published for {url}, download won't be verified"),
             }
-            cache_file.write_all(&buffer[..bytes_read])?;
-            pb.inc(bytes_read as u64);
+            return Ok(Remote {
+                url: url.to_string(),
+                total_size,
+                file_name,
+                checksum,
+                etag,
+                last_modified,
+                accepts_ranges,
+            });
         }
-        pb.finish_with_message("Download completed");
+        bail!("too many redirects for {url}")
+    }
 
-        Ok(file_name)
+    fn sidecar_checksum(&self, url: &reqwest::Url) -> Option<Checksum> {
+        let sidecar = format!("{url}.sha256");
+        debug!("looking for checksum sidecar {sidecar}");
+        let text = self
+            .client
+            .get(&sidecar)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text())
+            .ok()?;
+        Checksum::from_sidecar(Algorithm::Sha256, &text)
     }
 
-    fn download_to_file_monitored(
+    /// Download `remote` into `entry`. Bytes go to the entry's `.part` file,
+    /// which is renamed into place once complete and verified. A `.part` left
+    /// over by an interrupted or cancelled attempt is resumed with a `Range`
+    /// request, guarded by `If-Range` so a changed file is fetched in full.
+    /// A download starting from the first byte is read through `consume`.
+    fn download(
         &self,
-        url: &str,
-        file_path: &Path,
+        remote: &Remote,
+        entry: &mut CacheEntry,
         on_progress: &mut dyn FnMut(u64, u64),
         cancel: &AtomicBool,
-    ) -> Result<String> {
-        let head_response = self.client.head(url).send().context("HEAD")?;
-        let headers = head_response.headers().clone();
-        let total_size = headers
-            .get(header::CONTENT_LENGTH)
-            .and_then(|len| len.to_str().ok()?.parse::<u64>().ok())
-            .context("content length")?;
-
-        let file_name = headers
-            .get(header::CONTENT_DISPOSITION)
-            .and_then(|value| extract_filename_from_disposition(value.to_str().ok()?))
-            .unwrap_or("")
-            .to_string();
-
-        on_progress(0, total_size);
-
-        let mut response = self.client.get(url).send().context("GET")?;
-        if response.status() == 304 {
-            on_progress(total_size, total_size);
-            return Ok(file_name);
-        }
-
-        let mut cache_file = File::create(file_path).context("create cache file")?;
-        let mut buffer = [0u8; 8192];
-        let mut downloaded: u64 = 0;
-        loop {
+        consume: Option<Consumer>,
+    ) -> Result<()> {
+        let part_path = entry.part_path();
+        let total_size = remote.total_size;
+
+        let mut offset = resumable_len(entry, remote);
+        if offset == 0 && part_path.exists() {
+            debug!("discarding stale partial download {:?}", part_path);
+            std::fs::remove_file(&part_path)?;
+        }
+
+        // A stale data file goes first: the metadata below no longer
+        // describes it, and must never vouch for it if this download fails.
+        if entry.file_path().exists() {
+            std::fs::remove_file(entry.file_path())?;
+        }
+        // The metadata is written before the data so an interrupted download
+        // keeps the validators needed to resume it.
+        entry.metadata.file_name = remote.file_name.clone();
+        entry.metadata.checksum = remote
+            .checksum
+            .as_ref()
+            .map(|c| c.to_string())
+            .unwrap_or_default();
+        entry.metadata.etag = remote.etag.clone();
+        entry.metadata.last_modified = remote.last_modified.clone();
+        entry.set_content(0, "");
+        entry.save()?;
+
+        let mut request = self.client.get(&remote.url);
+        if offset > 0 {
+            debug!("resuming download at byte {offset}");
+            let validator = if remote.etag.is_empty() {
+                &remote.last_modified
+            } else {
+                &remote.etag
+            };
+            request = request
+                .header(header::RANGE, format!("bytes={offset}-"))
+                .header(header::IF_RANGE, validator);
+        }
+        let response = request
+            .send()
+            .context("Failed to send GET request")?
+            .error_for_status()?;
+        debug!("HTTP response status {}", response.status());
+
+        if offset > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+            debug!("server did not honour the range request, restarting download");
+            offset = 0;
+        }
+
+        let mut digests = Digests::new(remote.checksum.as_ref());
+        let part_file = if offset > 0 {
+            io::copy(&mut File::open(&part_path)?, &mut digests)?;
+            OpenOptions::new().append(true).open(&part_path)
+        } else {
+            File::create(&part_path)
+        }
+        .context("Failed to create cache file")?;
+
+        on_progress(offset, total_size);
+        let mut body = Body {
+            response,
+            part_file,
+            digests,
+            downloaded: offset,
+            total_size,
+            on_progress,
+            cancel,
+        };
+        let consumed = match (offset, consume) {
+            (0, Some(consume)) => consume(&mut body, &remote.file_name),
+            _ => Ok(()),
+        };
+        // Download whatever `consume` left unread.
+        let read = consumed.and_then(|()| {
+            io::copy(&mut body, &mut io::sink()).context("read chunk")?;
+            Ok(())
+        });
+        if let Err(e) = read {
+            // Cancelling surfaces as a read error, wrapped by whoever read.
             if cancel.load(Ordering::Relaxed) {
-                drop(cache_file);
-                let _ = std::fs::remove_file(file_path);
-                eyre::bail!("download cancelled");
+                bail!("download cancelled");
             }
-            let bytes_read = response.read(&mut buffer).context("read chunk")?;
-            if bytes_read == 0 {
-                break;
+            return Err(e);
+        }
+        let Body {
+            part_file,
+            digests,
+            downloaded,
+            ..
+        } = body;
+        part_file.sync_all()?;
+        drop(part_file);
+
+        if downloaded != total_size {
+            // Keep the partial file: the next attempt resumes from it.
+            bail!(
+                "download of {} stopped after {downloaded} of {total_size} bytes",
+                remote.url
+            );
+        }
+        let digest = match digests.finish(remote) {
+            Ok(digest) => digest,
+            Err(e) => {
+                let _ = std::fs::remove_file(&part_path);
+                return Err(e);
             }
-            cache_file.write_all(&buffer[..bytes_read])?;
-            downloaded += bytes_read as u64;
-            on_progress(downloaded.min(total_size), total_size);
+        };
+
+        debug!("renaming {:?} to {:?}", part_path, entry.file_path());
+        std::fs::rename(&part_path, entry.file_path())?;
+        entry.set_content(downloaded, &digest);
+        entry.metadata.fetched_at = unix_now();
+        entry.metadata.last_used = entry.metadata.fetched_at;
+        entry.save()
+    }
+}
+
+/// A download's response body, written to the `.part` file and hashed as it
+/// is read.
+struct Body<'a> {
+    response: Response,
+    part_file: File,
+    digests: Digests,
+    downloaded: u64,
+    total_size: u64,
+    on_progress: &'a mut dyn FnMut(u64, u64),
+    cancel: &'a AtomicBool,
+}
+
+impl Read for Body<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.cancel.load(Ordering::Relaxed) {
+            debug!("download cancelled, keeping partial file for resume");
+            return Err(io::Error::other("download cancelled"));
         }
+        let bytes_read = self.response.read(buf)?;
+        self.part_file.write_all(&buf[..bytes_read])?;
+        self.digests.write_all(&buf[..bytes_read])?;
+        self.downloaded += bytes_read as u64;
+        (self.on_progress)(self.downloaded.min(self.total_size), self.total_size);
+        Ok(bytes_read)
+    }
+}
+
+/// What `probe` learned about a download before fetching it.
+struct Remote {
+    /// Final URL after following redirects.
+    url: String,
+    total_size: u64,
+    file_name: String,
+    checksum: Option<Checksum>,
+    etag: String,
+    last_modified: String,
+    /// False when the server explicitly refuses range requests.
+    accepts_ranges: bool,
+}
+
+/// Hashes computed while downloading: the cache integrity digest, plus the
+/// published checksum when it uses a different algorithm.
+struct Digests {
+    digest: Hasher,
+    checksum: Option<Hasher>,
+}
+
+impl Digests {
+    fn new(checksum: Option<&Checksum>) -> Digests {
+        Digests {
+            digest: Hasher::new(DIGEST_ALGORITHM),
+            checksum: checksum
+                .filter(|c| c.algorithm != DIGEST_ALGORITHM)
+                .map(|c| Hasher::new(c.algorithm)),
+        }
+    }
+
+    /// Verify the published checksum, if any, and return the digest (hex).
+    fn finish(self, remote: &Remote) -> Result<String> {
+        let digest = self.digest.finalize_hex();
+        if let Some(expected) = &remote.checksum {
+            let actual = match self.checksum {
+                Some(hasher) => hasher.finalize_hex(),
+                None => digest.clone(),
+            };
+            expected.verify(&actual, &remote.url)?;
+        }
+        Ok(digest)
+    }
+}
+
+impl Write for Digests {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.digest.update(buf);
+        if let Some(checksum) = &mut self.checksum {
+            checksum.update(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Length of the entry's partial download if it can be resumed against
+/// `remote`: the server takes ranges, and the validators recorded when the
+/// partial download started still match. Zero means start over.
+fn resumable_len(entry: &CacheEntry, remote: &Remote) -> u64 {
+    let Ok(len) = std::fs::metadata(entry.part_path()).map(|m| m.len()) else {
+        return 0;
+    };
+    let meta = &entry.metadata;
+    let same_file = (!remote.etag.is_empty() && meta.etag == remote.etag)
+        || (!remote.last_modified.is_empty() && meta.last_modified == remote.last_modified);
+    if remote.accepts_ranges && same_file && len < remote.total_size {
+        len
+    } else {
+        0
+    }
+}
+
+/// Re-check a cached archive before it is extracted. A mismatch evicts the
+/// entry so the next attempt re-downloads.
+fn verify_cached(entry: &CacheEntry) -> Result<()> {
+    if let Err(e) = entry.verify() {
+        entry.remove()?;
+        return Err(e.wrap_err("the corrupt cache entry was deleted, retry to download it again"));
+    }
+    Ok(())
+}
+
+fn serve_cached_text(entry: &CacheEntry) -> Result<String> {
+    entry.read_text()
+}
+
+fn client_builder() -> ClientBuilder {
+    let mut client = Client::builder().timeout(args::timeout());
+
+    if let Some(proxy) = read_proxy_from_env() {
+        client = client.proxy(proxy);
+    }
+
+    if args::insecure() {
+        client = client.danger_accept_invalid_certs(true)
+    }
+    client
+}
+
+/// Offline mode was asked for something that was never cached.
+#[derive(Debug)]
+pub struct NotCached(pub String);
+
+impl Display for NotCached {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "offline mode: {} is not in the cache, run once with network access first",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for NotCached {}
+
+fn not_cached(url: &str) -> eyre::Report {
+    NotCached(url.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    fn temp_cache() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rsdk-http-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn client(cache_dir: &Path, offline: bool) -> CachedHttpClient {
+        CachedHttpClient {
+            offline,
+            cache_ttl: args::DEFAULT_CACHE_TTL,
+            ..CachedHttpClient::new(cache_dir)
+        }
+    }
+
+    #[test]
+    fn serves_fresh_text_and_revalidates_stale_text() {
+        let cache_dir = temp_cache();
+        let (base_url, log) =
+            test_server::serve_logged(|request| match request.header("If-None-Match") {
+                Some("\"v1\"") => (304, vec![], vec![]),
+                _ => (
+                    200,
+                    vec![
+                        ("ETag".to_string(), "\"v1\"".to_string()),
+                        (
+                            "Last-Modified".to_string(),
+                            "Sat, 01 Jun 2024 00:00:00 GMT".to_string(),
+                        ),
+                    ],
+                    b"java\nmaven".to_vec(),
+                ),
+            });
+        let url = format!("{base_url}/candidates/all");
+        let api = client(&cache_dir, false);
+
+        assert_eq!(api.get_text(&url).unwrap(), "java\nmaven");
+        // Within the TTL: served from the cache without a request.
+        assert_eq!(api.get_text(&url).unwrap(), "java\nmaven");
+        assert_eq!(log.lock().unwrap().len(), 1);
+
+        // Past the TTL: a conditional request, whose 304 refreshes the entry.
+        let cache = CacheManager::new(&cache_dir);
+        let mut entry = cache.get_cache_entry(&url);
+        entry.metadata.fetched_at = 0;
+        entry.save().unwrap();
+        assert_eq!(api.get_text(&url).unwrap(), "java\nmaven");
+        {
+            let log = log.lock().unwrap();
+            assert_eq!(log.len(), 2);
+            let revalidation = &log[1];
+            assert_eq!(revalidation.path, "/candidates/all");
+            assert_eq!(revalidation.header("If-None-Match"), Some("\"v1\""));
+            assert_eq!(
+                revalidation.header("If-Modified-Since"),
+                Some("Sat, 01 Jun 2024 00:00:00 GMT")
+            );
+        }
+        assert!(cache.get_cache_entry(&url).age() < args::DEFAULT_CACHE_TTL);
+        assert_eq!(api.get_text(&url).unwrap(), "java\nmaven");
+        assert_eq!(log.lock().unwrap().len(), 2);
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn offline_never_opens_a_socket() {
+        let cache_dir = temp_cache();
+        let (base_url, log) = test_server::serve_logged(|_| (200, vec![], b"fresh".to_vec()));
+        let cached = format!("{base_url}/candidates/all");
+        let mut entry = CacheManager::new(&cache_dir).get_cache_entry(&cached);
+        entry.write_text("cached").unwrap();
+        // Long past the TTL: online, this would be revalidated.
+        entry.metadata.fetched_at = 0;
+        entry.save().unwrap();
+
+        let api = client(&cache_dir, true);
+        assert_eq!(api.get_text(&cached).unwrap(), "cached");
+        let missing = format!("{base_url}/candidates/java/linux/versions/list");
+        let err = api.get_text(&missing).unwrap_err();
+        assert!(err.downcast_ref::<NotCached>().is_some(), "{err:#}");
+        let err = api
+            .get_cached_file(
+                &format!("{base_url}/broker/download/java/21"),
+                "java",
+                "21",
+                None,
+            )
+            .unwrap_err();
+        assert!(err.downcast_ref::<NotCached>().is_some(), "{err:#}");
+
+        assert!(log.lock().unwrap().is_empty());
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    /// The bytes of a 200 KiB download, and the header publishing its SHA-256.
+    fn archive() -> (Vec<u8>, (String, String)) {
+        let body: Vec<u8> = (0..200 * 1024).map(|i| (i % 251) as u8).collect();
+        let mut hasher = Hasher::new(Algorithm::Sha256);
+        hasher.update(&body);
+        let checksum = (
+            "X-Sdkman-Checksum-SHA-256".to_string(),
+            hasher.finalize_hex(),
+        );
+        (body, checksum)
+    }
+
+    /// A `.part` file holding the first `len` bytes of `body`, recorded as
+    /// fetched from a server tagging it `etag`.
+    fn partial_download(cache_dir: &Path, url: &str, body: &[u8], len: usize, etag: &str) {
+        let mut entry = CacheManager::new(cache_dir).get_cache_entry(url);
+        std::fs::write(entry.part_path(), &body[..len]).unwrap();
+        entry.metadata.etag = etag.to_string();
+        entry.save().unwrap();
+    }
+
+    /// The `Range` header of every GET in `log`.
+    fn ranges(log: &test_server::Log) -> Vec<Option<String>> {
+        log.lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.method == "GET")
+            .map(|r| r.header("Range").map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn resumes_a_partial_download() {
+        let cache_dir = temp_cache();
+        let (body, checksum) = archive();
+        let served = body.clone();
+        let (base_url, log) = test_server::serve_logged(move |request| {
+            let (status, mut headers, body) = test_server::ranged(request, "\"v1\"", &served);
+            headers.push(checksum.clone());
+            (status, headers, body)
+        });
+        let url = format!("{base_url}/broker/download/tool/1.0");
+        partial_download(&cache_dir, &url, &body, 1000, "\"v1\"");
+
+        let entry = client(&cache_dir, false)
+            .get_cached_file(&url, "tool", "1.0", None)
+            .unwrap();
+        assert_eq!(std::fs::read(entry.file_path()).unwrap(), body);
+        assert!(!entry.part_path().exists());
+        assert_eq!(ranges(&log), [Some("bytes=1000-".to_string())]);
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn restarts_when_the_server_ignores_the_range() {
+        let cache_dir = temp_cache();
+        let (body, checksum) = archive();
+        let served = body.clone();
+        let (base_url, log) = test_server::serve_logged(move |_| {
+            let headers = vec![("ETag".to_string(), "\"v1\"".to_string()), checksum.clone()];
+            (200, headers, served.clone())
+        });
+        let url = format!("{base_url}/broker/download/tool/1.0");
+        partial_download(&cache_dir, &url, &body, 1000, "\"v1\"");
+
+        let entry = client(&cache_dir, false)
+            .get_cached_file(&url, "tool", "1.0", None)
+            .unwrap();
+        assert_eq!(std::fs::read(entry.file_path()).unwrap(), body);
+        assert_eq!(ranges(&log), [Some("bytes=1000-".to_string())]);
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn discards_a_partial_download_of_a_changed_file() {
+        let cache_dir = temp_cache();
+        let (body, checksum) = archive();
+        let served = body.clone();
+        let (base_url, log) = test_server::serve_logged(move |request| {
+            let (status, mut headers, body) = test_server::ranged(request, "\"v2\"", &served);
+            headers.push(checksum.clone());
+            (status, headers, body)
+        });
+        let url = format!("{base_url}/broker/download/tool/1.0");
+        // Bytes of the previous release, which must not end up in the file.
+        partial_download(&cache_dir, &url, &[0xff; 1000], 1000, "\"v1\"");
+
+        let entry = client(&cache_dir, false)
+            .get_cached_file(&url, "tool", "1.0", None)
+            .unwrap();
+        assert_eq!(std::fs::read(entry.file_path()).unwrap(), body);
+        assert_eq!(ranges(&log), [None]);
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_a_cancelled_download_for_the_next_attempt() {
+        let cache_dir = temp_cache();
+        let (body, checksum) = archive();
+        let served = body.clone();
+        let (base_url, log) = test_server::serve_logged(move |request| {
+            let (status, mut headers, body) = test_server::ranged(request, "\"v1\"", &served);
+            headers.push(checksum.clone());
+            (status, headers, body)
+        });
+        let url = format!("{base_url}/broker/download/tool/1.0");
+        let api = client(&cache_dir, false);
+
+        let cancel = AtomicBool::new(false);
+        let err = api
+            .get_cached_file_monitored(
+                &url,
+                "tool",
+                "1.0",
+                &mut |bytes, _| cancel.store(bytes > 0, Ordering::Relaxed),
+                &cancel,
+                None,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("cancelled"), "{err:#}");
+        let entry = CacheManager::new(&cache_dir).get_cache_entry(&url);
+        assert!(!entry.file_path().exists());
+        let kept = std::fs::metadata(entry.part_path()).unwrap().len();
+        assert!(kept > 0 && kept < body.len() as u64, "{kept}");
+
+        let entry = api.get_cached_file(&url, "tool", "1.0", None).unwrap();
+        assert_eq!(std::fs::read(entry.file_path()).unwrap(), body);
+        assert_eq!(ranges(&log), [None, Some(format!("bytes={kept}-"))]);
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn a_failed_download_does_not_vouch_for_the_old_file() {
+        let cache_dir = temp_cache();
+        let (body, checksum) = archive();
+        // The probe succeeds, the download itself fails.
+        let base_url = test_server::serve(move |request| match request.method.as_str() {
+            "HEAD" => (200, vec![checksum.clone()], body.clone()),
+            _ => (500, vec![], vec![]),
+        });
+        let url = format!("{base_url}/broker/download/tool/1.0");
+        let mut entry = CacheManager::new(&cache_dir).get_cache_entry(&url);
+        std::fs::write(entry.file_path(), b"a truncated archive").unwrap();
+        entry.set_content(1000, "");
+        entry.save().unwrap();
+        assert!(!entry.is_valid());
 
-        Ok(file_name)
+        let err = client(&cache_dir, false)
+            .get_cached_file(&url, "tool", "1.0", None)
+            .unwrap_err();
+        assert!(err.to_string().contains("500"), "{err:#}");
+        let entry = CacheManager::new(&cache_dir).get_cache_entry(&url);
+        assert!(!entry.file_path().exists());
+        assert!(!entry.is_valid());
+        std::fs::remove_dir_all(&cache_dir).unwrap();
     }
 }
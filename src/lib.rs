@@ -4,11 +4,19 @@
 pub mod archive;
 pub mod args;
 pub mod cache;
+pub mod checksum;
+pub mod config;
+pub mod hooks;
 pub mod http_client;
 pub mod http_utils;
+pub mod output;
 pub mod rcfile;
 pub mod rsdk_home;
 pub mod sdkman_client;
 pub mod sdkman_decode;
 pub mod shell;
+#[cfg(test)]
+mod test_server;
 pub mod tool_version;
+pub mod upgrade;
+pub mod version;
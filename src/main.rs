@@ -3,9 +3,14 @@ mod tui;
 
 use clap::{CommandFactory, Parser};
 use eyre::bail;
-use log::debug;
-use rsdk::args::{Cli, Command, EnvSubcommand, Shell, ARGS};
-use rsdk::{args, rcfile, rsdk_home, sdkman_client, shell, tool_version::ToolVersion};
+use log::{debug, warn};
+use rsdk::args::{CacheSubcommand, Cli, Command, ConfigSubcommand, EnvSubcommand, Shell, ARGS};
+use rsdk::cache::{self, CacheManager};
+use rsdk::output::{self, Record};
+use rsdk::sdkman_decode::JavaVersionEntry;
+use rsdk::tool_version::{global_scope, report_resolved, resolve_installed, ToolVersion};
+use rsdk::{args, config, rcfile, rsdk_home, sdkman_client, shell, upgrade, version};
+use std::collections::HashSet;
 use std::io::Write;
 use std::{env, fs, io};
 
@@ -17,6 +22,10 @@ fn main() -> color_eyre::Result<()> {
     let cli = Cli::parse();
     let _ = ARGS.set(cli.clone());
 
+    if args::plain() {
+        colored::control::set_override(false);
+    }
+
     if args::debug() {
         env::set_var(RUST_LOG, "debug");
         env::set_var(RUST_BACKTRACE, "1");
@@ -26,6 +35,21 @@ fn main() -> color_eyre::Result<()> {
 
     env_logger::init();
 
+    let config_path = config::config_path();
+    let loaded = match config::Config::load(&config_path) {
+        // Still let `rsdk config` repair a broken file.
+        Err(e) if matches!(cli.command, Some(Command::Config { .. })) => {
+            warn!("{e:#}");
+            config::Config::default()
+        }
+        loaded => loaded?,
+    };
+    let _ = config::CONFIG.set(loaded);
+
+    if args::format().is_some() && !cli.command.as_ref().is_some_and(Command::has_records) {
+        bail!("--format json and tsv only apply to list, installed, current and default");
+    }
+
     let rsdk_home = rsdk_home::RsdkHome::new()?;
 
     if let Some(command) = &cli.command {
@@ -38,6 +62,7 @@ fn main() -> color_eyre::Result<()> {
                 // `current` symlink is what `use` / `env` flip, so PATH never
                 // needs to be rewritten after this (same model as SDKMAN).
                 for default_version in default_tools {
+                    rsdk_home.relink(&default_version.tool)?;
                     let current_bin = default_version
                         .path()
                         .parent()
@@ -61,9 +86,15 @@ fn main() -> color_eyre::Result<()> {
                     .filter(|p| !p.starts_with(rsdk_home.tools()))
                     .for_each(|p| paths.push(p));
 
+                // Keep later commands in this shell on the same root.
+                if args::home_from_flag() {
+                    shell::set_env_var_after_exit("RSDK_HOME", &rsdk_home.root.to_string_lossy())?;
+                }
+
                 let new_path = env::join_paths(paths)?;
                 debug!("updating PATH to {:?}", new_path);
                 shell::set_env_var_after_exit("PATH", &new_path.to_string_lossy())?;
+                rcfile::export_auto_env(config::config().auto_env.unwrap_or(false))?;
             }
             Command::Install {
                 tool,
@@ -95,11 +126,11 @@ fn main() -> color_eyre::Result<()> {
                         if *default
                             || ask(
                                 &format!(
-                                    "Do you want to make {tool} {} the new default? (Y/n): ",
+                                    "Do you want to make {tool} {} the new default?",
                                     tv.version
                                 ),
-                                true,
-                            )
+                                Some(true),
+                            )?
                         {
                             tv.make_default()?;
                             tv.make_current()?;
@@ -116,53 +147,43 @@ fn main() -> color_eyre::Result<()> {
                 }
             }
             Command::Uninstall { tool, version } | Command::Remove { tool, version } => {
-                let cv = ToolVersion::new(&rsdk_home, tool, version);
-
-                let was_default = cv.is_default();
-                let was_current = cv.is_current();
-
-                if was_default {
-                    debug!("deleting default symlink to deleted version");
-                    fs::remove_file(rsdk_home.default_symlink_path(tool))?;
-                }
-
-                cv.uninstall()?;
-                println!(
-                    "{} {} {}",
-                    cli_style::error("Uninstalled"),
-                    cli_style::accent(tool),
-                    cli_style::accent(version)
-                );
-
-                let vv: Vec<_> = rsdk_home.installed_versions(tool)?.collect();
-                match vv.len() {
-                    0 => {
-                        debug!("deleted last tool version, deleting tool dir too");
-                        fs::remove_dir_all(rsdk_home.tool_dir(tool))?
-                    }
-                    _ => {
-                        let new_cv = &vv[0];
-                        if was_default {
-                            new_cv.make_default()?;
-                        }
-                        if was_current {
-                            new_cv.make_current()?;
-                        }
-                        if was_default {
-                            println!(
-                                "{} {} {} {}",
-                                cli_style::default_(&new_cv.tool),
-                                cli_style::accent(&new_cv.version),
-                                cli_style::info("is the new"),
-                                cli_style::default_("default")
-                            )
-                        }
-                    }
-                }
+                uninstall(&rsdk_home, &ToolVersion::new(&rsdk_home, tool, version))?;
             }
-            Command::List { tool } => {
+            Command::List {
+                tool,
+                vendor,
+                major,
+                lts,
+            } => {
                 let api = sdkman_client::SdkManClient::new(&rsdk_home.cache());
-                if let Some(tool) = tool {
+                let filtered = vendor.is_some() || major.is_some() || *lts;
+                if filtered && tool.as_deref() != Some("java") {
+                    bail!("--vendor, --major and --lts only apply to java");
+                }
+                if filtered {
+                    let entries: Vec<_> = api
+                        .get_java_versions()?
+                        .into_iter()
+                        .filter(|e| vendor.as_ref().is_none_or(|v| e.matches_vendor(v)))
+                        .filter(|e| major.is_none() || e.major == *major)
+                        .filter(|e| !*lts || e.is_lts())
+                        .collect();
+                    print_java_entries(&rsdk_home, &entries)?;
+                } else if let Some(format) = args::format() {
+                    let records: Vec<_> = if let Some(tool) = tool {
+                        api.get_tool_versions(tool)?
+                            .iter()
+                            .map(|v| Record::version(&ToolVersion::new(&rsdk_home, tool, v)))
+                            .collect()
+                    } else {
+                        let installed = installed_tools(&rsdk_home)?;
+                        api.get_tools()?
+                            .iter()
+                            .map(|t| Record::tool(t, installed.contains(t)))
+                            .collect()
+                    };
+                    println!("{}", output::render(format, &records)?);
+                } else if let Some(tool) = tool {
                     println!("{}", api.get_tool_versions_text(tool)?)
                 } else {
                     println!("{}", api.get_tools_list_text()?)
@@ -173,93 +194,228 @@ fn main() -> color_eyre::Result<()> {
                     .all_installed()?
                     .filter(|tv| tool.as_ref().is_none_or(|t| tv.tool.eq(t)))
                     .collect();
-                installed.sort_by(|a, b| a.tool.cmp(&b.tool).then(a.version.cmp(&b.version)));
-
-                // Mark the current version with `*` and align the version column
-                // by padding tool names to the widest (like sdkman).
-                let width = installed.iter().map(|tv| tv.tool.len()).max().unwrap_or(0);
-                for tv in &installed {
-                    let marker = if tv.is_current() {
-                        cli_style::star("*")
-                    } else {
-                        " ".to_string()
-                    };
-                    let tool = if tv.is_default() {
-                        cli_style::default_(&tv.tool)
-                    } else {
-                        tv.tool.clone()
-                    };
-                    let version = if tv.is_current() {
-                        cli_style::current(&tv.version)
-                    } else {
-                        tv.version.clone()
-                    };
-                    println!("{marker} {:width$} {}", tool, version, width = width);
+                installed.sort_by(|a, b| {
+                    a.tool
+                        .cmp(&b.tool)
+                        .then_with(|| version::compare(&a.version, &b.version))
+                });
+                if let Some(format) = args::format() {
+                    let records: Vec<_> = installed.iter().map(Record::version).collect();
+                    println!("{}", output::render(format, &records)?);
+                } else {
+                    // Mark the current version with `*` and align the version column
+                    // by padding tool names to the widest (like sdkman).
+                    let width = installed.iter().map(|tv| tv.tool.len()).max().unwrap_or(0);
+                    for tv in &installed {
+                        let marker = if tv.is_current() {
+                            cli_style::star("*")
+                        } else {
+                            " ".to_string()
+                        };
+                        let tool = if tv.is_default() {
+                            cli_style::default_(&tv.tool)
+                        } else {
+                            tv.tool.clone()
+                        };
+                        let version = if tv.is_current() {
+                            cli_style::current(&tv.version)
+                        } else {
+                            tv.version.clone()
+                        };
+                        println!("{marker} {:width$} {}", tool, version, width = width);
+                    }
+                }
+            }
+            Command::Outdated {
+                tool,
+                any_major,
+                any_vendor,
+            } => {
+                let api = sdkman_client::SdkManClient::new(&rsdk_home.cache());
+                let scope = upgrade::Scope {
+                    any_major: *any_major,
+                    any_vendor: *any_vendor,
+                };
+                let outdated = upgrade::outdated(&rsdk_home, &api, tool.as_deref(), scope)?;
+                if outdated.is_empty() {
+                    println!("{}", cli_style::info("Everything is up to date"));
+                }
+                let tool_width = outdated
+                    .iter()
+                    .map(|o| o.installed.tool.len())
+                    .max()
+                    .unwrap_or(0);
+                let version_width = outdated
+                    .iter()
+                    .map(|o| o.installed.version.len())
+                    .max()
+                    .unwrap_or(0);
+                for o in &outdated {
+                    let line = format!(
+                        "{:tool_width$}  {:version_width$} -> {}  {}",
+                        o.installed.tool,
+                        o.installed.version,
+                        cli_style::accent(&o.latest),
+                        o.sdkman_default
+                            .as_ref()
+                            .map(|d| cli_style::dim(&format!("(sdkman default {d})")))
+                            .unwrap_or_default(),
+                        tool_width = tool_width,
+                        version_width = version_width,
+                    );
+                    println!("{}", line.trim_end());
+                }
+            }
+            Command::Upgrade {
+                tool,
+                default,
+                prune,
+                any_major,
+                any_vendor,
+            } => {
+                let api = sdkman_client::SdkManClient::new(&rsdk_home.cache());
+                let scope = upgrade::Scope {
+                    any_major: *any_major,
+                    any_vendor: *any_vendor,
+                };
+                let outdated = upgrade::outdated(&rsdk_home, &api, tool.as_deref(), scope)?;
+                if outdated.is_empty() {
+                    println!("{}", cli_style::info("Everything is up to date"));
+                }
+                let mut upgraded = HashSet::new();
+                for o in outdated {
+                    // current and default may both be behind the same release
+                    if upgraded.insert((o.installed.tool.clone(), o.latest.clone())) {
+                        let (tv, new_install) =
+                            ToolVersion::install(&rsdk_home, &o.installed.tool, &Some(o.latest))?;
+                        if new_install {
+                            println!(
+                                "{} {} {} -> {}",
+                                cli_style::star("Upgraded"),
+                                cli_style::accent(&tv.tool),
+                                o.installed.version,
+                                cli_style::accent(&tv.version)
+                            );
+                        } else {
+                            println!(
+                                "{} {} {} {}",
+                                cli_style::info("Tool"),
+                                cli_style::accent(&tv.tool),
+                                cli_style::accent(&tv.version),
+                                cli_style::dim("was already installed")
+                            );
+                        }
+                        if *default {
+                            tv.make_default()?;
+                            tv.make_current()?;
+                        } else if !*prune {
+                            println!(
+                                "{}",
+                                cli_style::dim(&format!(
+                                    "run 'rsdk default {} {}' to make it the default",
+                                    tv.tool, tv.version
+                                ))
+                            );
+                        }
+                    }
+                    if *prune {
+                        uninstall(&rsdk_home, &o.installed)?;
+                    }
                 }
             }
-            Command::Env { command } => {
+            Command::Env { command, file } => {
+                let file = file.as_deref();
                 if let Some(command) = command {
                     match command {
-                        EnvSubcommand::Init => rcfile::env_init(&rsdk_home)?,
-                        EnvSubcommand::Install => rcfile::env_install(&rsdk_home)?,
+                        EnvSubcommand::Init => rcfile::env_init(&rsdk_home, file)?,
+                        EnvSubcommand::Install { tool, version } => rcfile::env_install(
+                            &rsdk_home,
+                            file,
+                            tool.as_deref(),
+                            version.as_deref(),
+                        )?,
+                        EnvSubcommand::Add { tool, version } => {
+                            rcfile::env_add(&rsdk_home, file, tool, version.as_deref())?
+                        }
+                        EnvSubcommand::Remove { tool } => rcfile::env_remove(file, tool)?,
+                        EnvSubcommand::Pin => rcfile::env_pin(&rsdk_home, file)?,
                         EnvSubcommand::Clear => rcfile::env_clear(&rsdk_home)?,
+                        EnvSubcommand::Auto => rcfile::env_auto(&rsdk_home)?,
                     }
                 } else {
-                    rcfile::env_apply(&rsdk_home)?;
+                    rcfile::env_apply(&rsdk_home, file)?;
                 }
             }
             Command::Default { tool, version } => {
                 if let Some(tool) = tool {
                     if let Some(version) = version {
-                        let cv = ToolVersion::new(&rsdk_home, tool, version);
-                        if cv.is_installed() {
-                            cv.make_default()?
-                        } else {
-                            bail!("tool '{cv}' is not installed")
+                        match resolve_installed(&rsdk_home, tool, version)? {
+                            Some(cv) => {
+                                report_resolved(version, &cv);
+                                cv.make_default()?
+                            }
+                            None => bail!("tool '{tool} {version}' is not installed"),
                         }
                     } else {
                         if let Some(version) = rsdk_home.default_version(tool)? {
-                            println!("{version}");
+                            print_versions(&[version])?;
                         } else {
                             bail!("no default version set for tool '{}'", tool);
                         }
                     }
                 } else {
-                    for tv in rsdk_home.all_installed()? {
-                        if tv.is_default() {
-                            println!("{tv}");
-                        }
-                    }
+                    let mut defaults: Vec<_> = rsdk_home.all_defaults()?.collect();
+                    defaults.sort_by(|a, b| a.tool.cmp(&b.tool));
+                    print_versions(&defaults)?;
                 }
             }
             Command::Current { tool } => {
                 if let Some(tool) = tool {
                     match current_tv(&rsdk_home, tool)? {
-                        Some(tv) => println!("{tv}"),
+                        Some(tv) => print_versions(&[tv])?,
                         None => bail!("no current version of tool '{}'", tool),
                     }
                 } else {
+                    let mut current = vec![];
                     for tool in installed_tools(&rsdk_home)? {
-                        if let Some(tv) = current_tv(&rsdk_home, &tool)? {
-                            println!("{tv}");
-                        }
+                        current.extend(current_tv(&rsdk_home, &tool)?);
                     }
+                    print_versions(&current)?;
                 }
             }
-            Command::Use { tool, version } => {
+            Command::Use {
+                tool,
+                version,
+                global,
+                session,
+            } => {
+                // Without the shell integration there is no session to change.
+                if *session && args::envout().is_none() {
+                    bail!("--session only works through the rsdk shell function");
+                }
+                let global = global_scope(*global, *session);
+                let make_current = |tv: &ToolVersion| {
+                    if global {
+                        tv.make_current()
+                    } else {
+                        tv.make_session_current()
+                    }
+                };
                 if let Some(version) = version {
-                    let tv = ToolVersion::new(&rsdk_home, tool, version);
-                    if tv.is_installed() {
-                        tv.make_current()?;
+                    if let Some(tv) = resolve_installed(&rsdk_home, tool, version)? {
+                        report_resolved(version, &tv);
+                        make_current(&tv)?;
                     } else {
                         // SDKMAN offers to install a missing version on `use`.
+                        // Downloading is not a safe thing to assume, so there
+                        // is no default when running non-interactively.
                         if ask(
-                            &format!("{tool} {version} is not installed, install it now? (Y/n): "),
-                            true,
-                        ) {
+                            &format!("{tool} {version} is not installed, install it now?"),
+                            None,
+                        )? {
                             let (tv, _) =
                                 ToolVersion::install(&rsdk_home, tool, &Some(version.clone()))?;
-                            tv.make_current()?;
+                            make_current(&tv)?;
                             println!(
                                 "{} {} {}",
                                 cli_style::star("Installed"),
@@ -267,10 +423,7 @@ fn main() -> color_eyre::Result<()> {
                                 cli_style::accent(&tv.version)
                             );
                         } else {
-                            eprintln!(
-                                "{}",
-                                cli_style::error(&format!("'{tool} {version}' is not installed"))
-                            );
+                            bail!("'{tool} {version}' is not installed");
                         }
                     }
                 } else {
@@ -285,6 +438,129 @@ fn main() -> color_eyre::Result<()> {
                 fs::remove_dir_all(rsdk_home.cache())?;
                 fs::create_dir_all(rsdk_home.cache())?
             }
+            Command::Config { command } => match command {
+                ConfigSubcommand::List => {
+                    for (key, value) in config::list(&config_path)? {
+                        println!("{key} = {value}");
+                    }
+                }
+                ConfigSubcommand::Get { key } => match config::get(&config_path, key)? {
+                    Some(value) => println!("{value}"),
+                    None => bail!("'{key}' is not set in {config_path:?}"),
+                },
+                ConfigSubcommand::Set { key, value } => {
+                    config::set(&config_path, key, value)?;
+                    // Turn the directory-change hook on or off in this shell.
+                    if key == "auto_env" {
+                        rcfile::export_auto_env(value == "true")?;
+                    }
+                }
+                ConfigSubcommand::Unset { key } => {
+                    if !config::unset(&config_path, key)? {
+                        eprintln!("{}", cli_style::dim(&format!("'{key}' was not set")));
+                    }
+                    if key == "auto_env" {
+                        rcfile::export_auto_env(false)?;
+                    }
+                }
+            },
+            Command::Cache { command } => {
+                let cache = CacheManager::new(&rsdk_home.cache());
+                match command {
+                    CacheSubcommand::List => {
+                        let mut entries = cache.entries()?;
+                        entries.sort_by_key(|e| std::cmp::Reverse(e.last_used()));
+                        let label = |s: &str| {
+                            if s.is_empty() {
+                                "-".to_string()
+                            } else {
+                                s.to_string()
+                            }
+                        };
+                        let tool_width = entries
+                            .iter()
+                            .map(|e| label(&e.metadata.tool).len())
+                            .max()
+                            .unwrap_or(0)
+                            .max(4);
+                        let version_width = entries
+                            .iter()
+                            .map(|e| label(&e.metadata.version).len())
+                            .max()
+                            .unwrap_or(0)
+                            .max(7);
+                        println!(
+                            "{}",
+                            cli_style::dim(&format!(
+                                "{:tool_width$} {:version_width$} {:>10} {:>14}  URL",
+                                "TOOL", "VERSION", "SIZE", "LAST USED"
+                            ))
+                        );
+                        for entry in &entries {
+                            let url = if entry.metadata.url.is_empty() {
+                                cli_style::dim("(unknown)")
+                            } else {
+                                entry.metadata.url.clone()
+                            };
+                            println!(
+                                "{:tool_width$} {:version_width$} {:>10} {:>14}  {url}",
+                                label(&entry.metadata.tool),
+                                label(&entry.metadata.version),
+                                cache::format_size(entry.size()),
+                                cache::format_age(entry.last_used()),
+                            );
+                        }
+                        let total: u64 = entries.iter().map(|e| e.size()).sum();
+                        println!(
+                            "{} {} in {}",
+                            cli_style::info("Total"),
+                            cache::format_size(total),
+                            plural(entries.len(), "entry", "entries")
+                        );
+                    }
+                    CacheSubcommand::Prune {
+                        older_than,
+                        max_size,
+                        keep_installed,
+                    } => {
+                        let (removed, freed) = cache.prune(*older_than, *max_size, |entry| {
+                            *keep_installed
+                                && !entry.metadata.tool.is_empty()
+                                && ToolVersion::new(
+                                    &rsdk_home,
+                                    &entry.metadata.tool,
+                                    &entry.metadata.version,
+                                )
+                                .is_installed()
+                        })?;
+                        println!(
+                            "{} {} ({})",
+                            cli_style::info("Pruned"),
+                            plural(removed, "entry", "entries"),
+                            cache::format_size(freed)
+                        );
+                    }
+                    CacheSubcommand::Rm { tool, version } => {
+                        let matching: Vec<_> = cache
+                            .entries()?
+                            .into_iter()
+                            .filter(|e| e.metadata.tool == *tool && e.metadata.version == *version)
+                            .collect();
+                        if matching.is_empty() {
+                            bail!("no cached archive for '{tool} {version}'");
+                        }
+                        for entry in matching {
+                            entry.remove()?;
+                        }
+                        println!(
+                            "{} {} {}",
+                            cli_style::error("Removed cached"),
+                            cli_style::accent(tool),
+                            cli_style::accent(version)
+                        );
+                    }
+                }
+            }
             Command::Tui => {
                 color_eyre::install()?;
                 let result = tui::run(rsdk_home);
@@ -336,23 +612,183 @@ fn main() -> color_eyre::Result<()> {
 }
 
 /// Prompt the user with `prompt`, returning `default` when they just press
-/// enter or when input can't be read.
-pub fn ask(prompt: &str, default: bool) -> bool {
-    print!("{prompt}");
-    io::stdout().flush().expect("Failed to flush stdout");
+/// enter. `--yes` / `--no` (or `auto_answer`) answer without asking. When
+/// running non-interactively the default is used, and a prompt without a
+/// default (`None`) is an error rather than a guess. Any choice made without
+/// asking is reported.
+pub fn ask(prompt: &str, default: Option<bool>) -> color_eyre::Result<bool> {
+    let (answer, reason) = if let Some(answer) = args::auto_answer() {
+        (answer == config::Answer::Yes, "auto answer")
+    } else if args::non_interactive() {
+        match default {
+            Some(default) => (default, "non-interactive default"),
+            None => bail!(
+                "{prompt} (no default answer in non-interactive mode, rerun with --yes or --no)"
+            ),
+        }
+    } else {
+        loop {
+            print!("{prompt} ({}): ", choices(default));
+            io::stdout().flush()?;
 
-    let mut input = String::new();
-    match io::stdin().read_line(&mut input) {
-        Ok(_) => {
-            let response = input.trim().to_lowercase();
-            if response.is_empty() {
-                default
-            } else {
-                matches!(response.as_str(), "y" | "yes")
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input)? == 0 {
+                // End of input: nobody is left to answer.
+                println!();
+                match default {
+                    Some(default) => return Ok(default),
+                    None => {
+                        bail!("{prompt} (no answer before end of input, rerun with --yes or --no)")
+                    }
+                }
+            }
+            match (input.trim().to_lowercase().as_str(), default) {
+                ("y" | "yes", _) => return Ok(true),
+                ("n" | "no", _) => return Ok(false),
+                ("", Some(default)) => return Ok(default),
+                _ => continue,
+            }
+        }
+    };
+    println!(
+        "{prompt} ({}): {} {}",
+        choices(default),
+        if answer { "y" } else { "n" },
+        cli_style::dim(&format!("({reason})"))
+    );
+    Ok(answer)
+}
+
+/// `Y/n`, `y/N` or `y/n` depending on the default answer.
+fn choices(default: Option<bool>) -> &'static str {
+    match default {
+        Some(true) => "Y/n",
+        Some(false) => "y/N",
+        None => "y/n",
+    }
+}
+
+/// Uninstall `cv`, moving its `default` and `current` links to the latest
+/// remaining version, or removing the tool dir when none are left.
+fn uninstall(rsdk_home: &rsdk_home::RsdkHome, cv: &ToolVersion) -> color_eyre::Result<()> {
+    let tool = &cv.tool;
+    let was_default = cv.is_default();
+    let was_current = cv.is_current();
+
+    if was_default {
+        debug!("deleting default symlink to deleted version");
+        fs::remove_file(rsdk_home.default_symlink_path(tool))?;
+    }
+
+    cv.uninstall()?;
+    println!(
+        "{} {} {}",
+        cli_style::error("Uninstalled"),
+        cli_style::accent(tool),
+        cli_style::accent(&cv.version)
+    );
+
+    let vv: Vec<_> = rsdk_home.installed_versions(tool)?.collect();
+    match vv.len() {
+        0 => {
+            debug!("deleted last tool version, deleting tool dir too");
+            fs::remove_dir_all(rsdk_home.tool_dir(tool))?
+        }
+        _ => {
+            // Fall back to the latest remaining version.
+            let new_cv = vv
+                .iter()
+                .max_by(|a, b| version::compare(&a.version, &b.version))
+                .expect("at least one version left");
+            if was_default {
+                new_cv.make_default()?;
+            }
+            if was_current {
+                new_cv.make_current()?;
+            }
+            if was_default {
+                println!(
+                    "{} {} {} {}",
+                    cli_style::default_(&new_cv.tool),
+                    cli_style::accent(&new_cv.version),
+                    cli_style::info("is the new"),
+                    cli_style::default_("default")
+                )
             }
         }
-        Err(_) => default,
     }
+    Ok(())
+}
+
+/// Print `tool version` lines, or records when `--format` asks for them.
+fn print_versions(versions: &[ToolVersion]) -> color_eyre::Result<()> {
+    if let Some(format) = args::format() {
+        let records: Vec<_> = versions.iter().map(Record::version).collect();
+        println!("{}", output::render(format, &records)?);
+    } else {
+        for tv in versions {
+            println!("{tv}");
+        }
+    }
+    Ok(())
+}
+
+/// Print a filtered Java catalog as a table like SDKMAN's, marking installed
+/// versions with their local state, or as records with `--format`.
+fn print_java_entries(
+    home: &rsdk_home::RsdkHome,
+    entries: &[JavaVersionEntry],
+) -> color_eyre::Result<()> {
+    let versions: Vec<_> = entries
+        .iter()
+        .map(|e| ToolVersion::new(home, "java", &e.identifier))
+        .collect();
+    if let Some(format) = args::format() {
+        let records: Vec<_> = versions.iter().map(Record::version).collect();
+        println!("{}", output::render(format, &records)?);
+        return Ok(());
+    }
+    let vendor_width = entries.iter().map(|e| e.vendor.len()).max().unwrap_or(0);
+    let version_width = entries.iter().map(|e| e.version.len()).max().unwrap_or(0);
+    let id_width = entries
+        .iter()
+        .map(|e| e.identifier.len())
+        .max()
+        .unwrap_or(0);
+    for (entry, tv) in entries.iter().zip(&versions) {
+        let marker = if tv.is_current() {
+            cli_style::star("*")
+        } else {
+            " ".to_string()
+        };
+        let mut state = vec![];
+        if tv.is_installed() {
+            state.push("installed".to_string());
+            if tv.is_current() {
+                state.push(cli_style::current("current"));
+            }
+            if tv.is_default() {
+                state.push(cli_style::default_("default"));
+            }
+        }
+        let line = format!(
+            "{marker} {:vendor_width$}  {:version_width$}  {:id_width$}  {}",
+            entry.vendor,
+            entry.version,
+            entry.identifier,
+            state.join(", "),
+            vendor_width = vendor_width,
+            version_width = version_width,
+            id_width = id_width,
+        );
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+/// `1 entry` / `3 entries`.
+fn plural(n: usize, one: &str, many: &str) -> String {
+    format!("{n} {}", if n == 1 { one } else { many })
 }
 
 /// Resolve the current version of `tool`, materializing the `current` symlink
@@ -368,7 +804,7 @@ fn current_tv(home: &rsdk_home::RsdkHome, tool: &str) -> color_eyre::Result<Opti
         .expect("current version path has a version component");
     let tv = ToolVersion::new(home, tool, &version);
     // Converge legacy state onto the symlink model.
-    if !home.current_symlink_path(tool).exists() {
+    if !home.current_symlink_path(tool).exists() && home.session_version(tool).is_none() {
         tv.make_current()?;
     }
     Ok(Some(tv))
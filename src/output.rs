@@ -0,0 +1,137 @@
+//! Structured output for `--format json|tsv`, so scripts and IDE plugins can
+//! read listings without scraping the coloured text meant for people.
+
+use crate::args::Format;
+use crate::tool_version::ToolVersion;
+use color_eyre::Result;
+use serde::Serialize;
+
+/// One tool or tool version, as emitted by `list`, `installed`, `current` and
+/// `default`. Fields that don't apply (e.g. `version` when listing tools) are
+/// left empty.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Record {
+    pub tool: String,
+    pub version: Option<String>,
+    pub vendor: Option<String>,
+    pub installed: bool,
+    pub current: bool,
+    pub default: bool,
+    pub path: Option<String>,
+}
+
+const TSV_HEADER: &str = "tool\tversion\tvendor\tinstalled\tcurrent\tdefault\tpath";
+
+impl Record {
+    /// A record for a tool without a specific version.
+    pub fn tool(tool: &str, installed: bool) -> Record {
+        Record {
+            tool: tool.to_string(),
+            installed,
+            ..Record::default()
+        }
+    }
+
+    pub fn version(tv: &ToolVersion) -> Record {
+        let installed = tv.is_installed();
+        Record {
+            tool: tv.tool.clone(),
+            version: Some(tv.version.clone()),
+            vendor: tv.vendor().map(str::to_string),
+            installed,
+            current: installed && tv.is_current(),
+            default: installed && tv.is_default(),
+            path: installed.then(|| tv.path().to_string_lossy().into_owned()),
+        }
+    }
+
+    fn tsv(&self) -> String {
+        let opt = |s: &Option<String>| tsv_field(s.as_deref().unwrap_or_default());
+        [
+            tsv_field(&self.tool),
+            opt(&self.version),
+            opt(&self.vendor),
+            self.installed.to_string(),
+            self.current.to_string(),
+            self.default.to_string(),
+            opt(&self.path),
+        ]
+        .join("\t")
+    }
+}
+
+/// A TSV field, with backslashes, tabs and line breaks escaped (`\\`, `\t`,
+/// `\n`, `\r`) so that every record stays on one line.
+fn tsv_field(s: &str) -> String {
+    let mut field = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => field.push_str("\\\\"),
+            '\t' => field.push_str("\\t"),
+            '\n' => field.push_str("\\n"),
+            '\r' => field.push_str("\\r"),
+            c => field.push(c),
+        }
+    }
+    field
+}
+
+/// Render `records` in a structured `format`. `Plain` has no structured form
+/// and renders nothing; callers print their human-readable text instead.
+pub fn render(format: Format, records: &[Record]) -> Result<String> {
+    Ok(match format {
+        Format::Json => serde_json::to_string_pretty(records)?,
+        Format::Tsv => std::iter::once(TSV_HEADER.to_string())
+            .chain(records.iter().map(Record::tsv))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Format::Plain => String::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record::tool("maven", false),
+            Record {
+                tool: "java".to_string(),
+                version: Some("21.0.6-tem".to_string()),
+                vendor: Some("tem".to_string()),
+                installed: true,
+                current: true,
+                default: false,
+                path: Some("C:\\tools\\odd\tdir\n\"quoted\"".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn renders_json_records() {
+        let json = render(Format::Json, &records()).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0]["tool"], "maven");
+        assert_eq!(parsed[0]["version"], serde_json::Value::Null);
+        assert_eq!(parsed[1]["current"], true);
+        assert_eq!(parsed[1]["path"], "C:\\tools\\odd\tdir\n\"quoted\"");
+        assert!(json.contains(r#""path": "C:\\tools\\odd\tdir\n\"quoted\"""#));
+    }
+
+    #[test]
+    fn renders_one_tsv_line_per_record() {
+        let tsv = render(Format::Tsv, &records()).unwrap();
+        let lines: Vec<_> = tsv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                TSV_HEADER,
+                "maven\t\t\tfalse\tfalse\tfalse\t",
+                "java\t21.0.6-tem\ttem\ttrue\ttrue\tfalse\tC:\\\\tools\\\\odd\\tdir\\n\"quoted\"",
+            ]
+        );
+        assert!(lines.iter().all(|l| l.split('\t').count() == 7));
+        assert_eq!(render(Format::Plain, &records()).unwrap(), "");
+    }
+}
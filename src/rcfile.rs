@@ -1,75 +1,436 @@
+use crate::config::config;
 use crate::rsdk_home::RsdkHome;
-use crate::tool_version::ToolVersion;
+use crate::shell;
+use crate::tool_version::{
+    global_scope, make_all_current, report_resolved, resolve_installed, ToolVersion,
+};
+use crate::version;
 use eyre::bail;
-use std::collections::HashMap;
-use std::fs::File;
-use std::path::Path;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::{env, fs};
 
 pub const SDKMAN_RC: &str = ".sdkmanrc";
 
-type Sdkmanrc = HashMap<String, String>;
-
-pub fn env_apply(home: &RsdkHome) -> color_eyre::Result<()> {
-    if let Some(sdkmanrc) = load()? {
-        for tv in &sdkmanrc {
-            if !ToolVersion::new(home, tv.0, tv.1).is_installed() {
-                bail!(
-                    "Tool {} version {} is not installed, run 'rsdk env install' first.",
-                    tv.0,
-                    tv.1
-                )
+/// The `.sdkmanrc` last applied by `env auto` in this shell.
+pub const RSDK_ENV_FILE: &str = "RSDK_ENV_FILE";
+
+/// Set while `auto_env` is on; the shell hooks only run `env auto` then.
+pub const RSDK_AUTO_ENV: &str = "RSDK_AUTO_ENV";
+
+/// The contents of a `.sdkmanrc`, kept line by line so that rewriting it
+/// preserves comments, blank lines, key order, line endings and settings rsdk
+/// doesn't use (`sdkman_auto_env`), and only touches the entries that changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Sdkmanrc {
+    lines: Vec<Line>,
+}
+
+/// A line without its terminator, and the terminator: `"\n"`, `"\r\n"`, or
+/// `""` for a last line without one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Line {
+    text: String,
+    ending: &'static str,
+}
+
+impl Sdkmanrc {
+    pub fn parse(text: &str) -> Sdkmanrc {
+        let lines = text
+            .split_inclusive('\n')
+            .map(|raw| {
+                let (text, ending) = if let Some(text) = raw.strip_suffix("\r\n") {
+                    (text, "\r\n")
+                } else if let Some(text) = raw.strip_suffix('\n') {
+                    (text, "\n")
+                } else {
+                    (raw, "")
+                };
+                Line {
+                    text: text.to_string(),
+                    ending,
+                }
+            })
+            .collect();
+        Sdkmanrc { lines }
+    }
+
+    pub fn load(path: &Path) -> color_eyre::Result<Sdkmanrc> {
+        Ok(Sdkmanrc::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> color_eyre::Result<()> {
+        Ok(fs::write(path, self.to_string())?)
+    }
+
+    /// Every `key=value` entry, in file order.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| entry(&line.text))
+    }
+
+    /// The `tool=version` entries, leaving out SDKMAN settings.
+    pub fn tools(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries()
+            .filter(|(key, _)| !key.starts_with("sdkman_"))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Set `key` to `value`, rewriting its line in place (keeping the spacing
+    /// around `=`) or appending one with the file's line ending.
+    pub fn set(&mut self, key: &str, value: &str) {
+        for line in &mut self.lines {
+            if entry(&line.text).is_some_and(|(k, _)| k == key) {
+                let (prefix, old) = line.text.split_once('=').expect("entries contain '='");
+                let padding = &old[..old.len() - old.trim_start().len()];
+                line.text = format!("{prefix}={padding}{value}");
+                return;
+            }
+        }
+        let newline = self
+            .lines
+            .iter()
+            .map(|line| line.ending)
+            .find(|ending| !ending.is_empty())
+            .unwrap_or("\n");
+        // The new last line takes over whether the file ended with a newline.
+        let ending = match self.lines.last_mut() {
+            Some(last) => std::mem::replace(&mut last.ending, newline),
+            None => newline,
+        };
+        self.lines.push(Line {
+            text: format!("{key}={value}"),
+            ending,
+        });
+    }
+
+    /// Remove the line of `key`. Returns whether it was present.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let mut removed = false;
+        while let Some(index) = self
+            .lines
+            .iter()
+            .position(|line| entry(&line.text).is_some_and(|(k, _)| k == key))
+        {
+            let line = self.lines.remove(index);
+            // Removing the last line keeps whether the file ended with a newline.
+            if index == self.lines.len() {
+                if let Some(last) = self.lines.last_mut() {
+                    last.ending = line.ending;
+                }
             }
+            removed = true;
         }
-        for tv in sdkmanrc {
-            ToolVersion::new(home, &tv.0, &tv.1).make_current()?;
+        removed
+    }
+}
+
+impl Display for Sdkmanrc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            write!(f, "{}{}", line.text, line.ending)?;
         }
-    } else {
-        bail!("no .sdkmanrc file found in current directory.")
+        Ok(())
     }
-    Ok(())
 }
 
-pub fn env_init(home: &RsdkHome) -> color_eyre::Result<()> {
-    let mut kv = HashMap::new();
-    for tv in home.all_installed()? {
-        if tv.is_current() {
-            kv.insert(tv.tool, tv.version);
+/// The key and value of an entry line; `None` for comments and blank lines.
+fn entry(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim();
+    if trimmed.starts_with('#') {
+        return None;
+    }
+    let (key, value) = trimmed.split_once('=')?;
+    Some((key.trim(), value.trim()))
+}
+
+pub fn env_apply(home: &RsdkHome, file: Option<&Path>) -> color_eyre::Result<()> {
+    match load(file)? {
+        Some(sdkmanrc) => apply(home, &sdkmanrc, false),
+        None => bail!("no .sdkmanrc file found in current directory or its parents."),
+    }
+}
+
+/// Make the versions in `sdkmanrc` current. Versions may be specifiers
+/// (`java=21`), resolved against what is installed; `quiet` leaves out which
+/// version each one resolved to.
+fn apply(home: &RsdkHome, sdkmanrc: &Sdkmanrc, quiet: bool) -> color_eyre::Result<()> {
+    let mut resolved = vec![];
+    for (tool, version) in sdkmanrc.tools() {
+        match resolve_installed(home, tool, version)? {
+            Some(installed) => {
+                if !quiet {
+                    report_resolved(version, &installed);
+                }
+                resolved.push(installed)
+            }
+            None => bail!(
+                "Tool {tool} version {version} is not installed, run 'rsdk env install' first."
+            ),
         }
     }
-    save(kv)
+    make_all_current(&resolved, global_scope(false, false))
+}
+
+/// Write the current versions to `file`, or to `.sdkmanrc` in the current
+/// directory. An existing file keeps its other lines.
+pub fn env_init(home: &RsdkHome, file: Option<&Path>) -> color_eyre::Result<()> {
+    let path = file.unwrap_or(Path::new(SDKMAN_RC));
+    let mut sdkmanrc = load_or_default(path)?;
+    let mut current: Vec<ToolVersion> =
+        home.all_installed()?.filter(|tv| tv.is_current()).collect();
+    current.sort_by(|a, b| a.tool.cmp(&b.tool));
+    for tv in current {
+        sdkmanrc.set(&tv.tool, &tv.version);
+    }
+    save(path, &sdkmanrc)
 }
 
-pub fn env_install(home: &RsdkHome) -> color_eyre::Result<()> {
-    if let Some(sdkmanrc) = load()? {
-        for tv in sdkmanrc {
-            let (installed, _) = ToolVersion::install(home, &tv.0, &Some(tv.1))?;
-            installed.make_current()?;
+/// Install every tool in env, or only `tool` (at `version`, else the one in
+/// env, else SDKMAN's default), adding or updating its entry.
+pub fn env_install(
+    home: &RsdkHome,
+    file: Option<&Path>,
+    tool: Option<&str>,
+    version: Option<&str>,
+) -> color_eyre::Result<()> {
+    if let Some(tool) = tool {
+        let path = edit_target(file)?;
+        let mut sdkmanrc = load_or_default(&path)?;
+        let version = version.or(sdkmanrc.get(tool)).map(str::to_string);
+        let (installed, _) = ToolVersion::install(home, &tool.to_string(), &version)?;
+        make_all_current(std::slice::from_ref(&installed), global_scope(false, false))?;
+        // Keep a specifier as written (`java=21`) rather than what it resolved to.
+        sdkmanrc.set(tool, version.as_deref().unwrap_or(&installed.version));
+        return save(&path, &sdkmanrc);
+    }
+    if let Some(sdkmanrc) = load(file)? {
+        let mut installed = vec![];
+        for (tool, version) in sdkmanrc.tools() {
+            let (tv, _) =
+                ToolVersion::install(home, &tool.to_string(), &Some(version.to_string()))?;
+            installed.push(tv);
         }
-        Ok(())
+        make_all_current(&installed, global_scope(false, false))
     } else {
-        bail!("no .sdkmanrc file found in current directory.")
+        bail!("no .sdkmanrc file found in current directory or its parents.")
     }
 }
 
+/// Set `tool` to `version` in env, or to its current version.
+pub fn env_add(
+    home: &RsdkHome,
+    file: Option<&Path>,
+    tool: &str,
+    version: Option<&str>,
+) -> color_eyre::Result<()> {
+    let version = match version {
+        Some(version) => version.to_string(),
+        None => match home.current_version(tool)? {
+            Some(current) => current.version,
+            None => bail!("no current version of tool '{tool}', give one explicitly"),
+        },
+    };
+    let path = edit_target(file)?;
+    let mut sdkmanrc = load_or_default(&path)?;
+    sdkmanrc.set(tool, &version);
+    save(&path, &sdkmanrc)
+}
+
+pub fn env_remove(file: Option<&Path>, tool: &str) -> color_eyre::Result<()> {
+    let path = edit_target(file)?;
+    let mut sdkmanrc = load_or_default(&path)?;
+    if !sdkmanrc.remove(tool) {
+        bail!("tool '{tool}' is not in {}", path.display());
+    }
+    save(&path, &sdkmanrc)
+}
+
+/// Replace version specifiers (`java=21`) with the installed versions they
+/// resolve to, so everyone using the file gets the same build.
+pub fn env_pin(home: &RsdkHome, file: Option<&Path>) -> color_eyre::Result<()> {
+    let path = edit_target(file)?;
+    let mut sdkmanrc = load_or_default(&path)?;
+    let specifiers: Vec<(String, String)> = sdkmanrc
+        .tools()
+        .filter(|(_, version)| version::is_specifier(version))
+        .map(|(tool, version)| (tool.to_string(), version.to_string()))
+        .collect();
+    for (tool, spec) in specifiers {
+        match resolve_installed(home, &tool, &spec)? {
+            Some(installed) => {
+                report_resolved(&spec, &installed);
+                sdkmanrc.set(&tool, &installed.version)
+            }
+            None => {
+                bail!("Tool {tool} version {spec} is not installed, run 'rsdk env install' first.")
+            }
+        }
+    }
+    save(&path, &sdkmanrc)
+}
+
 pub fn env_clear(home: &RsdkHome) -> color_eyre::Result<()> {
-    for tv in home.all_defaults()? {
-        tv.make_current()?
+    let defaults: Vec<ToolVersion> = home.all_defaults()?.collect();
+    make_all_current(&defaults, global_scope(false, false))?;
+    shell::unset_env_var_after_exit(RSDK_ENV_FILE)?;
+    Ok(())
+}
+
+/// Run by the shell hooks at startup and on every directory change when
+/// `auto_env` is set: apply the `.sdkmanrc` found from the current
+/// directory, or revert to the defaults when leaving the last one applied.
+/// Does nothing when the same file still applies, so prompts stay fast.
+pub fn env_auto(home: &RsdkHome) -> color_eyre::Result<()> {
+    let enabled = config().auto_env.unwrap_or(false);
+    export_auto_env(enabled)?;
+    if !enabled {
+        return Ok(());
+    }
+    let applied = env::var_os(RSDK_ENV_FILE).map(PathBuf::from);
+    let found = find(&env::current_dir()?);
+    if found == applied {
+        return Ok(());
+    }
+    match found {
+        Some(file) => {
+            // Recorded first: a file that can't be applied (a tool missing)
+            // is reported once, not again at every prompt.
+            shell::set_env_var_after_exit(RSDK_ENV_FILE, &file.to_string_lossy())?;
+            apply(home, &Sdkmanrc::load(&file)?, true)?;
+        }
+        None => {
+            println!("Leaving {}", applied.unwrap_or_default().display());
+            env_clear(home)?;
+        }
+    }
+    Ok(())
+}
+
+/// Tell the shell hooks whether to run `env auto` on directory change, so
+/// they don't start rsdk on every `cd` while `auto_env` is off.
+pub fn export_auto_env(enabled: bool) -> color_eyre::Result<()> {
+    if enabled {
+        shell::set_env_var_after_exit(RSDK_AUTO_ENV, "1")?;
+    } else {
+        shell::unset_env_var_after_exit(RSDK_AUTO_ENV)?;
     }
     Ok(())
 }
 
-fn load() -> color_eyre::Result<Option<Sdkmanrc>> {
-    let path = Path::new(SDKMAN_RC);
+/// The `.sdkmanrc` that applies in `dir`: the first one found walking up
+/// from `dir`, stopping at the git root (the directory holding `.git`) or the
+/// filesystem root.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        let path = dir.join(SDKMAN_RC);
+        if path.is_file() {
+            return Some(path);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// The file edited by `env add`, `remove` and `pin`: `file`, else the
+/// `.sdkmanrc` found from the current directory, else a new one there.
+fn edit_target(file: Option<&Path>) -> color_eyre::Result<PathBuf> {
+    let dir = env::current_dir()?;
+    Ok(match file {
+        Some(file) => file.to_path_buf(),
+        None => find(&dir).unwrap_or_else(|| dir.join(SDKMAN_RC)),
+    })
+}
+
+fn load_or_default(path: &Path) -> color_eyre::Result<Sdkmanrc> {
     if path.exists() {
-        let file = File::open(path)?;
-        Ok(Some(serde_ini::from_read(&file)?))
+        Sdkmanrc::load(path)
     } else {
-        Ok(None)
+        Ok(Sdkmanrc::default())
     }
 }
 
-fn save(sdkmanrc: Sdkmanrc) -> color_eyre::Result<()> {
-    let path = SDKMAN_RC;
-    let file = File::create(path)?;
-    Ok(serde_ini::to_writer(&file, &sdkmanrc)?)
+/// Save `sdkmanrc` to `path` and report the file that changed.
+fn save(path: &Path, sdkmanrc: &Sdkmanrc) -> color_eyre::Result<()> {
+    sdkmanrc.save(path)?;
+    println!("Updated {}", path.display());
+    Ok(())
+}
+
+/// Load `file`, or the `.sdkmanrc` found from the current directory, and
+/// report which file was used.
+fn load(file: Option<&Path>) -> color_eyre::Result<Option<Sdkmanrc>> {
+    let path = match file {
+        Some(file) if !file.is_file() => bail!("{} not found", file.display()),
+        Some(file) => file.to_path_buf(),
+        None => match find(&env::current_dir()?) {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+    println!("Using {}", path.display());
+    Ok(Some(Sdkmanrc::load(&path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RC: &str = "\
+# Enable auto-env through the sdkman_auto_env config
+sdkman_auto_env=true
+
+maven = 3.9.9
+java=21.0.6-tem
+";
+
+    #[test]
+    fn keeps_comments_order_and_settings() {
+        let mut rc = Sdkmanrc::parse(RC);
+        assert_eq!(rc.to_string(), RC);
+        assert_eq!(
+            rc.tools().collect::<Vec<_>>(),
+            [("maven", "3.9.9"), ("java", "21.0.6-tem")]
+        );
+        assert_eq!(rc.get("sdkman_auto_env"), Some("true"));
+
+        rc.set("maven", "3.9.10");
+        rc.set("gradle", "8.11.1");
+        assert!(rc.remove("java"));
+        assert!(!rc.remove("java"));
+        assert_eq!(
+            rc.to_string(),
+            RC.replace("3.9.9", "3.9.10")
+                .replace("java=21.0.6-tem\n", "")
+                + "gradle=8.11.1\n"
+        );
+    }
+
+    #[test]
+    fn keeps_line_endings() {
+        let crlf = RC.replace('\n', "\r\n");
+        let mut rc = Sdkmanrc::parse(&crlf);
+        assert_eq!(rc.to_string(), crlf);
+        assert_eq!(rc.get("maven"), Some("3.9.9"));
+        rc.set("maven", "3.9.10");
+        rc.set("gradle", "8.11.1");
+        assert_eq!(
+            rc.to_string(),
+            crlf.replace("3.9.9", "3.9.10") + "gradle=8.11.1\r\n"
+        );
+
+        let unterminated = "java=21.0.6-tem\nmaven=3.9.9";
+        let mut rc = Sdkmanrc::parse(unterminated);
+        assert_eq!(rc.to_string(), unterminated);
+        rc.set("gradle", "8.11.1");
+        assert_eq!(rc.to_string(), format!("{unterminated}\ngradle=8.11.1"));
+        assert!(rc.remove("gradle"));
+        assert_eq!(rc.to_string(), unterminated);
+        assert!(rc.remove("maven"));
+        assert_eq!(rc.to_string(), "java=21.0.6-tem");
+    }
 }
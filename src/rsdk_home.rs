@@ -1,6 +1,9 @@
-use crate::tool_version::{resolve_symlink, ToolVersion};
+use crate::args;
+use crate::config::config;
+use crate::tool_version::{relink_relative, resolve_symlink, ToolVersion};
 use directories::UserDirs;
 use fs::create_dir_all;
+use std::ffi::OsStr;
 use std::path::PathBuf;
 use std::{fs, io};
 
@@ -14,9 +17,17 @@ impl RsdkHome {
         let user_dirs = UserDirs::new().expect("Failed to get user directories");
         let home_dir = user_dirs.home_dir();
 
-        let rsdk_dir = home_dir.join(".rsdk");
-
-        RsdkHome::at(rsdk_dir)
+        let rsdk_dir = match args::home().or_else(|| config().home.clone()) {
+            // `~/...` is expanded since TOML has no notion of it.
+            Some(home) => match home.strip_prefix("~") {
+                Ok(rest) => home_dir.join(rest),
+                Err(_) => home,
+            },
+            None => home_dir.join(".rsdk"),
+        };
+
+        // Paths derived from the root end up in PATH and `*_HOME`.
+        RsdkHome::at(std::path::absolute(rsdk_dir)?)
     }
 
     /// Create an `RsdkHome` rooted at an arbitrary directory. Used by tests to
@@ -52,14 +63,39 @@ impl RsdkHome {
             .find(|version| version.is_current()))
     }
 
+    /// The version dir this shell's `PATH` points at for `tool` instead of the
+    /// shared `current/bin`, after a session-scoped `use`.
+    pub fn session_version(&self, tool: &str) -> Option<PathBuf> {
+        self.session_version_in(tool, &std::env::var_os("PATH")?)
+    }
+
+    /// [`Self::session_version`] for the given `PATH` value.
+    pub fn session_version_in(&self, tool: &str, path: &OsStr) -> Option<PathBuf> {
+        let tool_dir = self.tool_dir(tool);
+        let current = self.current_symlink_path(tool);
+        std::env::split_paths(path).find_map(|p| {
+            let version_dir = p.parent()?;
+            (p.ends_with("bin")
+                && version_dir.parent() == Some(tool_dir.as_path())
+                && version_dir != current)
+                .then(|| version_dir.to_path_buf())
+        })
+    }
+
     /// Resolve the active version's install path for `tool`, in priority order:
-    /// the `current` symlink, then the `default` symlink, then the tool's
-    /// `*_HOME` environment variable. The last two cover installs and shells
-    /// that predate the `current` symlink. Returns `None` if nothing resolves
-    /// to an existing directory under the tool dir.
+    /// this shell's session version, the `current` symlink, then the `default`
+    /// symlink, then the tool's `*_HOME` environment variable. The last two
+    /// cover installs and shells that predate the `current` symlink. Returns
+    /// `None` if nothing resolves to an existing directory under the tool dir.
     pub fn resolve_current(&self, tool: &str) -> Option<PathBuf> {
+        self.resolve_current_in(tool, &std::env::var_os("PATH").unwrap_or_default())
+    }
+
+    /// [`Self::resolve_current`] for the given `PATH` value.
+    pub fn resolve_current_in(&self, tool: &str, path: &OsStr) -> Option<PathBuf> {
         let tool_dir = self.tool_dir(tool);
         let candidates = [
+            self.session_version_in(tool, path),
             resolve_symlink(&self.current_symlink_path(tool)),
             resolve_symlink(&self.default_symlink_path(tool)),
             std::env::var_os(crate::tool_version::home_env(tool)).map(PathBuf::from),
@@ -70,6 +106,13 @@ impl RsdkHome {
             .find(|path| path.is_dir() && path.starts_with(&tool_dir))
     }
 
+    /// Make the tool's `current` and `default` symlinks relative, repairing
+    /// ones that still point into the root's previous location.
+    pub fn relink(&self, tool: &str) -> color_eyre::Result<()> {
+        relink_relative(&self.current_symlink_path(tool))?;
+        relink_relative(&self.default_symlink_path(tool))
+    }
+
     pub fn installed_versions<'a>(
         &'a self,
         tool: &'a str,
@@ -116,6 +159,7 @@ impl RsdkHome {
     pub fn cache(&self) -> PathBuf {
         self.root.join("cache")
     }
+
     pub fn temp(&self) -> PathBuf {
         self.root.join("temp")
     }
@@ -1,7 +1,14 @@
 use crate::cache::CacheEntry;
-use crate::http_client::CachedHttpClient;
-use crate::sdkman_decode::{decode_java_versions, decode_versions};
+use crate::config;
+use crate::http_client::{CachedHttpClient, Consumer, NotCached};
+use crate::sdkman_decode::{
+    decode_java_entries, decode_java_versions, decode_versions, JavaVersionEntry,
+};
 use color_eyre::Result;
+use eyre::eyre;
+use log::warn;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::str;
 
@@ -20,33 +27,95 @@ pub static PLATFORM: &str = "darwinx64";
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub static PLATFORM: &str = "darwinarm64";
 
+/// Used when neither `RSDK_API_BASE_URL` nor configured mirrors say otherwise.
+pub const DEFAULT_API_BASE_URL: &str = "https://api.sdkman.io/2";
+
 pub struct SdkManClient {
     http_client: CachedHttpClient,
-    base_url: String,
+    /// API base URLs tried in order, see [`SdkManClient::with_failover`].
+    mirrors: Vec<String>,
+    tool_mirrors: BTreeMap<String, Vec<String>>,
     platform: &'static str,
 }
 
 impl SdkManClient {
     pub fn new(cache_dir: &Path) -> Self {
         // Allow overriding the API base (used by the VHS demo and offline
-        // tests); defaults to the real SDKMAN API.
-        let base_url = std::env::var("RSDK_API_BASE_URL")
-            .unwrap_or_else(|_| "https://api.sdkman.io/2".to_string());
+        // tests); it replaces any configured mirrors.
+        let (mirrors, tool_mirrors) = match std::env::var("RSDK_API_BASE_URL") {
+            Ok(base_url) => (vec![base_url], BTreeMap::new()),
+            Err(_) => {
+                let config = config::config();
+                (config.mirrors.clone(), config.tool_mirrors.clone())
+            }
+        };
+        let mirrors = if mirrors.is_empty() {
+            vec![DEFAULT_API_BASE_URL.to_string()]
+        } else {
+            mirrors
+        };
         Self {
             http_client: CachedHttpClient::new(cache_dir),
-            base_url,
+            mirrors,
+            tool_mirrors,
             platform: PLATFORM,
         }
     }
 
+    /// Mirrors serving `tool`, or the general ones for requests that are not
+    /// about a specific tool.
+    fn mirrors_for(&self, tool: Option<&str>) -> &[String] {
+        tool.and_then(|t| self.tool_mirrors.get(t))
+            .filter(|m| !m.is_empty())
+            .unwrap_or(&self.mirrors)
+    }
+
+    /// Run `request` against each mirror's base URL in turn, moving on to the
+    /// next one when a mirror is unreachable, answers with a 5xx, or (in
+    /// offline mode) has nothing cached.
+    fn with_failover<T>(
+        &self,
+        tool: Option<&str>,
+        request: impl Fn(&str) -> Result<T>,
+    ) -> Result<T> {
+        let mirrors = self.mirrors_for(tool);
+        let mut last_error = None;
+        for (i, base_url) in mirrors.iter().enumerate() {
+            match request(base_url) {
+                Ok(value) => return Ok(value),
+                Err(e) if is_failover_error(&e) && i + 1 < mirrors.len() => {
+                    warn!("mirror {base_url} failed ({e}), trying the next one");
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| eyre!("no API mirror configured")))
+    }
+
     pub fn get_text(&self, uri: &str) -> Result<String> {
-        let url = format!("{}{}", self.base_url, uri);
-        self.http_client.get_text(&url)
+        self.get_tool_text(None, uri)
+    }
+
+    /// Fetch `uri` from the first mirror that answers. When the network
+    /// fails on every mirror, the first stale cached copy is served instead.
+    fn get_tool_text(&self, tool: Option<&str>, uri: &str) -> Result<String> {
+        self.with_failover(tool, |base_url| {
+            self.http_client.get_text(&format!("{base_url}{uri}"))
+        })
+        .or_else(|e| {
+            if !is_network_error(&e) {
+                return Err(e);
+            }
+            self.mirrors_for(tool)
+                .iter()
+                .find_map(|base_url| self.http_client.get_stale_text(&format!("{base_url}{uri}")))
+                .ok_or(e)
+        })
     }
 
     #[allow(unused)]
     pub fn get_api_version(&self) -> Result<String> {
-        let base_url = &self.base_url;
         self.get_text("/broker/download/sdkman/version/stable")
     }
 
@@ -68,9 +137,10 @@ impl SdkManClient {
 
     pub fn get_tool_versions_text(&self, tool: &str) -> Result<String> {
         let platform = &self.platform;
-        self.get_text(&format!(
-            "/candidates/{tool}/{platform}/versions/list?installed="
-        ))
+        self.get_tool_text(
+            Some(tool),
+            &format!("/candidates/{tool}/{platform}/versions/list?installed="),
+        )
     }
 
     pub fn get_tool_versions(&self, tool: &str) -> Result<Vec<String>> {
@@ -83,17 +153,33 @@ impl SdkManClient {
         Ok(versions)
     }
 
+    /// The Java catalog with vendor, distribution and status of each version.
+    pub fn get_java_versions(&self) -> Result<Vec<JavaVersionEntry>> {
+        Ok(decode_java_entries(&self.get_tool_versions_text("java")?))
+    }
+
     pub fn get_default_version(&self, tool: &str) -> Result<String> {
-        self.get_text(&format!("/candidates/default/{tool}"))
+        self.get_tool_text(Some(tool), &format!("/candidates/default/{tool}"))
     }
 
-    pub fn get_cached_file(&self, tool: &str, version: &str) -> Result<CacheEntry> {
+    /// The archive of `tool` `version`, through the cache. A fresh download
+    /// is also read through `consume`, see `Consumer`.
+    pub fn get_cached_file(
+        &self,
+        tool: &str,
+        version: &str,
+        consume: Option<Consumer>,
+    ) -> Result<CacheEntry> {
         let platform = &self.platform;
-        let url = format!(
-            "{}/broker/download/{tool}/{version}/{platform}",
-            self.base_url
-        );
-        self.http_client.get_cached_file(&url)
+        // `with_failover` takes an `Fn`, the consumer is `FnMut`.
+        let consume = RefCell::new(consume);
+        self.with_failover(Some(tool), |base_url| {
+            let url = format!("{base_url}/broker/download/{tool}/{version}/{platform}");
+            let mut consume = consume.borrow_mut();
+            let consume = consume.as_mut().map(|c| &mut **c as Consumer);
+            self.http_client
+                .get_cached_file(&url, tool, version, consume)
+        })
     }
 
     /// Monitored variant: reports progress and honours a cancel flag.
@@ -103,19 +189,93 @@ impl SdkManClient {
         version: &str,
         on_progress: &mut dyn FnMut(u64, u64),
         cancel: &std::sync::atomic::AtomicBool,
+        consume: Option<Consumer>,
     ) -> Result<CacheEntry> {
         let platform = &self.platform;
-        let url = format!(
-            "{}/broker/download/{tool}/{version}/{platform}",
-            self.base_url
-        );
-        self.http_client
-            .get_cached_file_monitored(&url, on_progress, cancel)
+        // `with_failover` takes an `Fn`, the callbacks are `FnMut`.
+        let on_progress = RefCell::new(on_progress);
+        let consume = RefCell::new(consume);
+        self.with_failover(Some(tool), |base_url| {
+            let url = format!("{base_url}/broker/download/{tool}/{version}/{platform}");
+            let mut consume = consume.borrow_mut();
+            self.http_client.get_cached_file_monitored(
+                &url,
+                tool,
+                version,
+                *on_progress.borrow_mut(),
+                cancel,
+                consume.as_mut().map(|c| &mut **c as Consumer),
+            )
+        })
     }
 
-    #[allow(unused)]
     pub fn get_post_install(&self, tool: &str, version: &str) -> Result<String> {
         let platform = &self.platform;
-        self.get_text(&format!("/hooks/post/{tool}/{version}/{platform}"))
+        self.get_tool_text(
+            Some(tool),
+            &format!("/hooks/post/{tool}/{version}/{platform}"),
+        )
+    }
+}
+
+/// Errors for which a stale cached copy beats no answer: the request failed
+/// in transport, or the server failed.
+fn is_network_error(e: &eyre::Report) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.status().is_none_or(|s| s.is_server_error()))
+}
+
+/// Errors telling that a resource doesn't exist: the server answered 404, or
+/// has no cached copy while offline.
+pub fn is_missing(e: &eyre::Report) -> bool {
+    e.downcast_ref::<NotCached>().is_some()
+        || e.downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.status() == Some(reqwest::StatusCode::NOT_FOUND))
+}
+
+/// Errors worth retrying on another mirror: the mirror could not be reached,
+/// failed server-side, or has no cached copy while offline.
+fn is_failover_error(e: &eyre::Report) -> bool {
+    if e.downcast_ref::<NotCached>().is_some() {
+        return true;
+    }
+    e.downcast_ref::<reqwest::Error>().is_some_and(|e| {
+        e.is_connect() || e.is_timeout() || e.status().is_some_and(|s| s.is_server_error())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheManager;
+    use crate::test_server;
+
+    fn client(cache_dir: &Path, mirrors: Vec<String>) -> SdkManClient {
+        SdkManClient {
+            http_client: CachedHttpClient::new(cache_dir),
+            mirrors,
+            tool_mirrors: BTreeMap::new(),
+            platform: PLATFORM,
+        }
+    }
+
+    #[test]
+    fn tries_every_mirror_before_serving_stale_text() {
+        let cache_dir = std::env::temp_dir().join(format!("rsdk-mirrors-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let down = test_server::unreachable();
+        let up = test_server::serve(|_| (200, vec![], b"fresh".to_vec()));
+
+        // An old copy from the first mirror, due for revalidation.
+        let cache = CacheManager::new(&cache_dir);
+        let mut entry = cache.get_cache_entry(&format!("{down}/candidates/all"));
+        entry.write_text("stale").unwrap();
+
+        let api = client(&cache_dir, vec![down.clone(), up]);
+        assert_eq!(api.get_text("/candidates/all").unwrap(), "fresh");
+
+        let api = client(&cache_dir, vec![down, test_server::unreachable()]);
+        assert_eq!(api.get_text("/candidates/all").unwrap(), "stale");
+        std::fs::remove_dir_all(&cache_dir).unwrap();
     }
 }
@@ -1,3 +1,5 @@
+use crate::version::Version;
+
 pub fn decode_versions(versions: &str) -> Vec<String> {
     let mut sepcount = 0;
     let mut vertab: Vec<Vec<&str>> = versions
@@ -30,11 +32,71 @@ pub fn decode_versions(versions: &str) -> Vec<String> {
     vervec
 }
 
-pub fn decode_java_versions(versions: &str) -> Vec<String> {
+/// One row of SDKMAN's Java versions table:
+///
+/// ```text
+///  Vendor        | Use | Version      | Dist    | Status     | Identifier
+/// --------------------------------------------------------------------------------
+///  Temurin       | >>> | 21.0.6       | tem     | installed  | 21.0.6-tem
+///                |     | 17.0.14      | tem     |            | 17.0.14-tem
+/// ```
+///
+/// The vendor is only printed on the first row of each vendor's block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JavaVersionEntry {
+    pub vendor: String,
+    /// `>>>` in the Use column: the version SDKMAN considers in use.
+    pub in_use: bool,
+    pub version: String,
+    /// Distribution code, also the identifier's suffix (`tem`, `graalce`...).
+    pub distribution: String,
+    /// `installed`, `local only` or empty.
+    pub status: String,
+    pub identifier: String,
+    /// Java feature release, e.g. 21 (17 for GraalVM's `22.3.r17`).
+    pub major: Option<u32>,
+}
+
+impl JavaVersionEntry {
+    /// Long-term support release, see [`is_lts_identifier`].
+    pub fn is_lts(&self) -> bool {
+        is_lts_identifier(&self.identifier)
+    }
+
+    /// Match a `--vendor` filter against the vendor name or distribution code.
+    pub fn matches_vendor(&self, vendor: &str) -> bool {
+        self.vendor.eq_ignore_ascii_case(vendor) || self.distribution.eq_ignore_ascii_case(vendor)
+    }
+}
+
+pub fn is_lts_major(major: u32) -> bool {
+    major == 8 || major == 11 || (major >= 17 && (major - 17).is_multiple_of(4))
+}
+
+/// Whether a Java identifier (`21.0.6-tem`) is a long-term support release.
+pub fn is_lts_identifier(identifier: &str) -> bool {
+    let version = Version::parse(identifier);
+    let release = identifier
+        .strip_suffix(version.vendor().unwrap_or_default())
+        .unwrap_or(identifier);
+    !version.is_pre_release() && java_major(release).is_some_and(is_lts_major)
+}
+
+/// The Java feature release of a version: the `r<N>` segment of GraalVM-style
+/// versions (`22.3.r17`), else the leading number (`21.0.6`, `25.ea.1`).
+pub fn java_major(version: &str) -> Option<u32> {
+    let segments = || version.split(['.', '-', '+']);
+    segments()
+        .find_map(|s| s.strip_prefix('r').and_then(|n| n.parse().ok()))
+        .or_else(|| segments().next()?.parse().ok())
+}
+
+pub fn decode_java_entries(versions: &str) -> Vec<JavaVersionEntry> {
     let mut dash_lines = 0;
     let mut eq_lines = 0;
+    let mut vendor = String::new();
 
-    let mut vertab: Vec<Vec<&str>> = versions
+    versions
         .lines()
         .filter(|l| {
             if l.starts_with("---") {
@@ -48,12 +110,73 @@ pub fn decode_java_versions(versions: &str) -> Vec<String> {
             }
         })
         .map(|l| l.split("|").map(|x| x.trim()).collect::<Vec<_>>())
-        .filter(|v| !v.is_empty())
-        .collect();
+        .filter(|v| v.len() >= 6)
+        .map(|v| {
+            if !v[0].is_empty() {
+                vendor = v[0].to_string();
+            }
+            JavaVersionEntry {
+                vendor: vendor.clone(),
+                in_use: v[1] == ">>>",
+                version: v[2].to_string(),
+                distribution: v[3].to_string(),
+                status: v[4].to_string(),
+                identifier: v[5].to_string(),
+                major: java_major(v[2]),
+            }
+        })
+        .collect()
+}
 
-    let mut vervec = Vec::new();
-    for v in &mut vertab {
-        vervec.push(v[5].to_string());
+pub fn decode_java_versions(versions: &str) -> Vec<String> {
+    decode_java_entries(versions)
+        .into_iter()
+        .map(|e| e.identifier)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JAVA_LIST: &str = "\
+================================================================================
+Available Java Versions for Linux 64bit
+================================================================================
+ Vendor        | Use | Version      | Dist    | Status     | Identifier
+--------------------------------------------------------------------------------
+ GraalVM CE    |     | 22.3.r17     | grl     |            | 22.3.r17-grl
+ Temurin       | >>> | 21.0.6       | tem     | installed  | 21.0.6-tem
+               |     | 17.0.14      | tem     | local only | 17.0.14-tem
+               |     | 25.ea.7      | tem     |            | 25.ea.7-tem
+================================================================================
+Omit Identifier to install default version 21.0.6-tem:
+    $ sdk install java
+================================================================================
+";
+
+    #[test]
+    fn decodes_java_table() {
+        let entries = decode_java_entries(JAVA_LIST);
+        assert_eq!(entries.len(), 4);
+
+        let tem17 = &entries[2];
+        assert_eq!(tem17.vendor, "Temurin");
+        assert_eq!(tem17.distribution, "tem");
+        assert_eq!(tem17.status, "local only");
+        assert_eq!(tem17.major, Some(17));
+        assert!(tem17.is_lts());
+        assert!(entries[1].in_use);
+
+        assert_eq!(entries[0].major, Some(17));
+        assert_eq!(entries[3].major, Some(25));
+        assert!(!entries[3].is_lts());
+        assert!(entries[3].matches_vendor("TEM"));
+        assert!(entries[3].matches_vendor("temurin"));
+
+        assert_eq!(
+            decode_java_versions(JAVA_LIST),
+            ["22.3.r17-grl", "21.0.6-tem", "17.0.14-tem", "25.ea.7-tem"]
+        );
     }
-    vervec
 }
@@ -5,25 +5,39 @@ use std::io;
 use std::io::Write;
 
 pub fn set_env_var_after_exit(name: &str, value: &str) -> io::Result<()> {
+    debug!("setting {} to {}", name, value);
+    emit_after_exit(|shell| match shell {
+        Shell::PowerShell => format!("$env:{name} = '{value}'"),
+        Shell::Bash | Shell::Zsh => format!("export {name}=\"{value}\""),
+        Shell::Fish => format!("set -gx {name} '{value}'"),
+        // nushell cannot evaluate shell statements at runtime, so emit
+        // one nuon record per variable; the adapter merges them and
+        // applies them via `load-env` (PATH is split into a list there).
+        Shell::Nushell => {
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("{{{name}:\"{escaped}\"}}")
+        }
+    })
+}
+
+pub fn unset_env_var_after_exit(name: &str) -> io::Result<()> {
+    debug!("unsetting {}", name);
+    emit_after_exit(|shell| match shell {
+        Shell::PowerShell => format!("Remove-Item Env:{name} -ErrorAction SilentlyContinue"),
+        Shell::Bash | Shell::Zsh => format!("unset {name}"),
+        Shell::Fish => format!("set -e {name}"),
+        // The nushell adapter hides variables whose value is null.
+        Shell::Nushell => format!("{{{name}:null}}"),
+    })
+}
+
+/// Append the shell-specific statement built by `command` to the `--envout`
+/// file, which the shell wrapper sources once rsdk exits.
+fn emit_after_exit(command: impl FnOnce(Shell) -> String) -> io::Result<()> {
     if let Some(shell) = args::shell() {
         if let Some(envout) = args::envout() {
             let mut file = OpenOptions::new().append(true).open(&envout)?;
-
-            debug!("setting {} to {}", name, value);
-
-            // Emit shell-specific environment variable instructions
-            let set_cmd = match shell {
-                Shell::PowerShell => format!("$env:{name} = '{value}'"),
-                Shell::Bash | Shell::Zsh => format!("export {name}=\"{value}\""),
-                Shell::Fish => format!("set -gx {name} '{value}'"),
-                // nushell cannot evaluate shell statements at runtime, so emit
-                // one nuon record per variable; the adapter merges them and
-                // applies them via `load-env` (PATH is split into a list there).
-                Shell::Nushell => {
-                    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
-                    format!("{{{name}:\"{escaped}\"}}")
-                }
-            };
+            let set_cmd = command(shell);
             if args::debug() {
                 writeln!(file, "echo eval: {set_cmd}")?;
             }
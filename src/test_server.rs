@@ -0,0 +1,123 @@
+//! A minimal HTTP/1.1 server for unit tests of the download and mirror code.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A canned answer: status, extra headers and body.
+pub type Response = (u16, Vec<(String, String)>, Vec<u8>);
+
+/// What the server was asked.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// The value of header `name`, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Every request a server received, in order.
+pub type Log = Arc<Mutex<Vec<Request>>>;
+
+/// Serve `handler(request)` on a free local port until the test process
+/// exits. Returns the base URL (`http://127.0.0.1:<port>`).
+pub fn serve(handler: impl Fn(&Request) -> Response + Send + 'static) -> String {
+    serve_logged(handler).0
+}
+
+/// Like `serve`, also returning the log of requests received.
+pub fn serve_logged(handler: impl Fn(&Request) -> Response + Send + 'static) -> (String, Log) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let log = Log::default();
+    let received = log.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = answer(stream, &handler, &received);
+        }
+    });
+    (base_url, log)
+}
+
+/// A base URL nothing listens on, for unreachable mirrors.
+pub fn unreachable() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+/// Answer `request` for `body`, tagged `etag`, as a server taking range
+/// requests does: a `Range: bytes=N-` whose `If-Range` (if any) still matches
+/// gets the rest of the body with 206, anything else all of it with 200.
+pub fn ranged(request: &Request, etag: &str, body: &[u8]) -> Response {
+    let mut headers = vec![("ETag".to_string(), etag.to_string())];
+    let start = request
+        .header("Range")
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.strip_suffix('-'))
+        .and_then(|start| start.parse::<usize>().ok())
+        .filter(|_| request.header("If-Range").is_none_or(|v| v == etag))
+        .filter(|&start| start < body.len());
+    match start {
+        Some(start) => {
+            let end = body.len() - 1;
+            let total = body.len();
+            headers.push((
+                "Content-Range".to_string(),
+                format!("bytes {start}-{end}/{total}"),
+            ));
+            (206, headers, body[start..].to_vec())
+        }
+        None => (200, headers, body.to_vec()),
+    }
+}
+
+fn answer(
+    stream: TcpStream,
+    handler: &impl Fn(&Request) -> Response,
+    log: &Log,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut headers = vec![];
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let request = Request {
+        method: parts.next().unwrap_or_default().to_string(),
+        path: parts.next().unwrap_or_default().to_string(),
+        headers,
+    };
+    let (status, headers, body) = handler(&request);
+    let method = request.method.clone();
+    log.lock().unwrap().push(request);
+
+    let mut stream = stream;
+    write!(stream, "HTTP/1.1 {status} X\r\nConnection: close\r\n")?;
+    write!(stream, "Content-Length: {}\r\n", body.len())?;
+    for (name, value) in headers {
+        write!(stream, "{name}: {value}\r\n")?;
+    }
+    write!(stream, "\r\n")?;
+    if method != "HEAD" {
+        stream.write_all(&body)?;
+    }
+    stream.flush()
+}
@@ -1,15 +1,23 @@
 use crate::rsdk_home::RsdkHome;
+use crate::sdkman_decode::is_lts_identifier;
+use crate::version::{self, Version};
 use crate::{sdkman_client, shell};
 use eyre::bail;
 use log::debug;
+use std::ffi::{OsStr, OsString};
 use std::fmt::{Display, Formatter};
-use std::fs;
 use std::fs::create_dir_all;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::{env, fs};
 use symlink::remove_symlink_dir;
 
-use crate::archive::{extract_tgz, extract_zip};
+use crate::archive::{extract, extract_stream, find_root, ArchiveFormat, Limits};
+use crate::args;
 use crate::cache::CacheEntry;
+use crate::config::{config, UseScope};
+use crate::hooks::{self, Layout};
+use crate::http_client::Consumer;
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
@@ -51,6 +59,15 @@ impl ToolVersion {
         home_env(&self.tool)
     }
 
+    /// The distribution code ending Java version identifiers (`tem` in
+    /// `21.0.6-tem`). Other tools have a single vendor.
+    pub fn vendor(&self) -> Option<&str> {
+        match self.tool.as_str() {
+            "java" => self.version.rsplit_once('-').map(|(_, vendor)| vendor),
+            _ => None,
+        }
+    }
+
     pub fn install(
         home: &RsdkHome,
         tool: &String,
@@ -59,6 +76,7 @@ impl ToolVersion {
         let api = sdkman_client::SdkManClient::new(&home.cache());
         let version = match version {
             None => api.get_default_version(tool)?,
+            Some(v) if version::is_specifier(v) => resolve_available(&api, tool, v)?,
             Some(v) => v.clone(),
         };
 
@@ -71,9 +89,10 @@ impl ToolVersion {
         let temp_dir = home.temp();
         let work_dir = temp_dir.join("work");
 
-        let archive = api.get_cached_file(tool, &version)?;
-        debug!("archive is {:?}", archive.file_path());
-        tv.install_from_file(&archive, &work_dir, true)?;
+        let layout = hooks::post_install_layout(&api, tool, &version)?;
+        tv.download_and_install(&work_dir, &layout, |consume| {
+            api.get_cached_file(tool, &version, consume)
+        })?;
         Ok((tv, true))
     }
 
@@ -96,47 +115,70 @@ impl ToolVersion {
         let temp_dir = home.temp();
         let work_dir = temp_dir.join("work");
 
-        let archive = api.get_cached_file_monitored(tool, version, on_progress, cancel)?;
-        debug!("archive is {:?}", archive.file_path());
-        tv.install_from_file(&archive, &work_dir, true)?;
+        let layout = hooks::post_install_layout(&api, tool, version)?;
+        tv.download_and_install(&work_dir, &layout, |consume| {
+            api.get_cached_file_monitored(tool, version, on_progress, cancel, consume)
+        })?;
         Ok((tv, true))
     }
 
+    /// Get the archive with `download`, which extracts tarballs into
+    /// `work_dir` as they arrive through the consumer it is given, then
+    /// install it. A failed download leaves nothing extracted behind.
+    fn download_and_install(
+        &self,
+        work_dir: &Path,
+        layout: &Layout,
+        download: impl FnOnce(Option<Consumer>) -> color_eyre::Result<CacheEntry>,
+    ) -> color_eyre::Result<()> {
+        let mut streamed = false;
+        let mut stream = |input: &mut dyn Read, file_name: &str| -> color_eyre::Result<()> {
+            // Reset when this attempt fails, so that a download resumed from
+            // another mirror, which is not streamed, is extracted in full.
+            streamed = extract_while_downloading(input, file_name, work_dir)
+                .inspect_err(|_| streamed = false)?;
+            Ok(())
+        };
+        let archive = download(stream_consumer(layout, &mut stream))
+            .inspect_err(|_| discard_work_dir(work_dir))?;
+        debug!("archive is {:?}", archive.file_path());
+        self.install_from_file(&archive, work_dir, layout, streamed, true)
+    }
+
+    /// Install `archive`, or the files already extracted from it into
+    /// `work_dir` while it downloaded when `streamed`.
     fn install_from_file(
         &self,
         archive: &CacheEntry,
         work_dir: &Path,
+        layout: &Layout,
+        streamed: bool,
         force: bool,
     ) -> color_eyre::Result<()> {
-        if let Err(e) = extract_zip(&archive.file_path(), work_dir) {
-            debug!("file is not a zip: {:?}", e);
-            if let Err(e) = extract_tgz(&archive.file_path(), work_dir) {
-                debug!("file is not a tgz: {:?}", e);
-                bail!("file {:?} is neither a zip nor a tgz", archive.file_path())
-            }
+        if streamed {
+            debug!("archive was extracted while downloading");
+        } else if let Layout::Binary { name } = layout {
+            clear_work_dir(work_dir)?;
+            hooks::place_binary(&archive.file_path(), &work_dir.join(&self.version), name)?;
+        } else {
+            clear_work_dir(work_dir)?;
+            let format = ArchiveFormat::detect(&archive.file_path(), &archive.metadata.file_name)?;
+            extract(
+                &archive.file_path(),
+                format,
+                work_dir,
+                &Limits::configured(),
+            )?;
         }
 
         // extraction complete, proceed to move to final dest
         let target_dir = &self.path();
-
-        let entries = fs::read_dir(work_dir)?
-            .filter_map(|res| res.ok())
-            .collect::<Vec<_>>();
-
-        if entries.len() != 1 {
-            bail!(format!(
-                "Expected exactly one entry in {:?}, found {}",
-                work_dir,
-                entries.len()
-            ));
-        }
-
-        let entry = &entries[0];
-        let entry_path = entry.path();
-
-        if !entry_path.is_dir() {
-            bail!(format!("{:?} is not a directory", entry_path));
-        }
+        let strategy = config()
+            .tool_roots
+            .get(&self.tool)
+            .copied()
+            .unwrap_or_else(|| layout.root_strategy());
+        let entry_path = find_root(work_dir, strategy)?;
 
         if target_dir.exists() {
             if force {
@@ -187,11 +229,37 @@ impl ToolVersion {
     /// corresponding `*_HOME` environment variable for the wrapper to apply.
     /// `PATH` is intentionally left untouched: it already contains the stable
     /// `<tool>/current/bin` entry emitted by `rsdk init`, so flipping the
-    /// symlink is all that is needed (same model as SDKMAN).
+    /// symlink is all that is needed (same model as SDKMAN). Only a shell that
+    /// was given a session version gets its `current/bin` entry back.
     pub fn make_current(&self) -> color_eyre::Result<()> {
-        point_symlink(&self.rsdk.current_symlink_path(&self.tool), &self.path())?;
-        shell::set_env_var_after_exit(&self.home(), &self.path().to_string_lossy())?;
-        Ok(())
+        make_all_current(std::slice::from_ref(self), true)
+    }
+
+    /// Use this version in the calling shell only: `PATH` and `*_HOME` point
+    /// at the version dir instead of the shared `current` symlink, so other
+    /// shells and IDEs keep their version.
+    pub fn make_session_current(&self) -> color_eyre::Result<()> {
+        make_all_current(std::slice::from_ref(self), false)
+    }
+
+    /// `path` with `bin` in place of the tool's entries, or first if there
+    /// were none.
+    fn path_pointing_at(&self, path: &OsStr, bin: &Path) -> color_eyre::Result<OsString> {
+        let tool_dir = self.rsdk.tool_dir(&self.tool);
+        let mut paths: Vec<PathBuf> = vec![];
+        let mut replaced = false;
+        for p in env::split_paths(path) {
+            if !p.starts_with(&tool_dir) {
+                paths.push(p);
+            } else if !replaced {
+                paths.push(bin.to_path_buf());
+                replaced = true;
+            }
+        }
+        if !replaced {
+            paths.insert(0, bin.to_path_buf());
+        }
+        Ok(env::join_paths(paths)?)
     }
 
     pub fn is_installed(&self) -> bool {
@@ -209,6 +277,130 @@ impl ToolVersion {
     }
 }
 
+/// Start from an empty work dir, a failed install may have left files.
+fn clear_work_dir(work_dir: &Path) -> color_eyre::Result<()> {
+    if work_dir.exists() {
+        fs::remove_dir_all(work_dir)?;
+    }
+    Ok(())
+}
+
+/// Drop what was extracted from a download that failed, e.g. its checksum.
+fn discard_work_dir(work_dir: &Path) {
+    if let Err(e) = clear_work_dir(work_dir) {
+        debug!("could not remove {work_dir:?}: {e}");
+    }
+}
+
+/// Where a download goes as it arrives: tarballs are extracted on the fly,
+/// bare binaries are not archives at all.
+fn stream_consumer<'a>(layout: &Layout, stream: Consumer<'a>) -> Option<Consumer<'a>> {
+    match layout {
+        Layout::Binary { .. } => None,
+        _ => Some(stream),
+    }
+}
+
+/// Extract a download into `work_dir` as it arrives. False when it is not a
+/// tarball and must be extracted once complete.
+fn extract_while_downloading(
+    input: &mut dyn Read,
+    file_name: &str,
+    work_dir: &Path,
+) -> color_eyre::Result<bool> {
+    // Also clears what an attempt on another mirror extracted.
+    clear_work_dir(work_dir)?;
+    extract_stream(input, file_name, work_dir, &Limits::configured())
+}
+
+/// Resolve a version specifier (`21`, `3.9.x`, `latest`, `lts-tem`) against
+/// the versions SDKMAN offers for `tool`. Java versions of the vendor of
+/// SDKMAN's default are preferred.
+fn resolve_available(
+    api: &sdkman_client::SdkManClient,
+    tool: &str,
+    spec: &str,
+) -> color_eyre::Result<String> {
+    let available = api.get_tool_versions(tool)?;
+    let preferred = match tool {
+        "java" => Some(api.get_default_version(tool)?),
+        _ => None,
+    };
+    let preferred = preferred.as_deref().map(Version::parse);
+    let preferred_vendor = preferred.as_ref().and_then(Version::vendor);
+    match version::resolve(spec, &available, preferred_vendor, is_lts_identifier) {
+        Some(resolved) => Ok(resolved.clone()),
+        None => bail!("no {tool} version matches '{spec}'"),
+    }
+}
+
+/// The installed version of `tool` designated by `spec`, an identifier or a
+/// specifier. An installed version named exactly `spec` (`gradle 8.5`)
+/// designates itself. Never touches the network.
+pub fn resolve_installed(
+    home: &RsdkHome,
+    tool: &str,
+    spec: &str,
+) -> color_eyre::Result<Option<ToolVersion>> {
+    let exact = ToolVersion::new(home, tool, spec);
+    if exact.is_installed() || !version::is_specifier(spec) {
+        return Ok(exact.is_installed().then_some(exact));
+    }
+    let installed: Vec<String> = home
+        .installed_versions(tool)?
+        .map(|tv| tv.version)
+        .collect();
+    // Prefer the vendor of the current version when switching majors.
+    let current = home.current_version(tool)?;
+    let preferred_vendor = current.as_ref().and_then(|tv| tv.vendor());
+    Ok(
+        version::resolve(spec, &installed, preferred_vendor, is_lts_identifier)
+            .map(|resolved| ToolVersion::new(home, tool, resolved)),
+    )
+}
+
+/// Tell which version a specifier (`21`, `lts`) designated.
+pub fn report_resolved(spec: &str, tv: &ToolVersion) {
+    if tv.version != spec {
+        println!("Resolved {} {spec} to {}", tv.tool, tv.version);
+    }
+}
+
+/// Whether `use` and `env` change the version for every shell (the `current`
+/// symlink) rather than only the calling one: with `--global`, else unless
+/// `--session` when `use_scope = "global"` or there is no shell integration
+/// whose session could be changed.
+pub fn global_scope(global: bool, session: bool) -> bool {
+    global
+        || (!session && (config().use_scope == Some(UseScope::Global) || args::envout().is_none()))
+}
+
+/// Make each of `tvs` current, for every shell when `global` (see
+/// [`ToolVersion::make_current`]) or else for the calling one only (see
+/// [`ToolVersion::make_session_current`]). `PATH` is emitted once, with the
+/// entries of all the tools changed.
+pub fn make_all_current(tvs: &[ToolVersion], global: bool) -> color_eyre::Result<()> {
+    let original = env::var_os("PATH").unwrap_or_default();
+    let mut path = original.clone();
+    for tv in tvs {
+        if global {
+            let current = tv.rsdk.current_symlink_path(&tv.tool);
+            point_symlink(&current, &tv.path())?;
+            if tv.rsdk.session_version_in(&tv.tool, &path).is_some() {
+                path = tv.path_pointing_at(&path, &current.join("bin"))?;
+            }
+        } else {
+            path = tv.path_pointing_at(&path, &tv.bin())?;
+        }
+        shell::set_env_var_after_exit(&tv.home(), &tv.path().to_string_lossy())?;
+    }
+    if path != original {
+        debug!("updating PATH to {:?}", path);
+        shell::set_env_var_after_exit("PATH", &path.to_string_lossy())?;
+    }
+    Ok(())
+}
+
 pub fn home_env(tool: &str) -> String {
     format!("{}_HOME", tool.to_uppercase())
 }
@@ -220,10 +412,28 @@ fn point_symlink(link: &Path, target: &Path) -> color_eyre::Result<()> {
         debug!("removing previous symlink {:?}", link);
         remove_symlink_dir(link)?;
     }
+    // `current` and `default` sit next to the versions they point at; a
+    // relative target keeps them valid when the whole root is moved.
+    let target = match (target.parent(), target.file_name()) {
+        (Some(dir), Some(name)) if link.parent() == Some(dir) => Path::new(name),
+        _ => target,
+    };
     debug!("creating symlink {:?} -> {:?}", link, target);
     Ok(symlink::symlink_dir(target, link)?)
 }
 
+/// Rewrite a symlink with an absolute target (as created by older versions)
+/// into the relative form, following a moved root if needed.
+pub(crate) fn relink_relative(link: &Path) -> color_eyre::Result<()> {
+    let Ok(raw) = fs::read_link(link) else {
+        return Ok(());
+    };
+    match resolve_symlink(link) {
+        Some(target) if raw.is_absolute() && target.is_dir() => point_symlink(link, &target),
+        _ => Ok(()),
+    }
+}
+
 /// True if `link` is a symlink that resolves to `target`.
 fn symlink_points_at(link: &Path, target: &Path) -> bool {
     resolve_symlink(link).is_some_and(|resolved| path_eq(&resolved, target))
@@ -232,13 +442,23 @@ fn symlink_points_at(link: &Path, target: &Path) -> bool {
 /// Resolve a symlink to the absolute path it points at, resolving relative
 /// targets against the link's parent. Returns `None` if `link` is not a
 /// readable symlink.
+///
+/// Absolute targets written before links became relative break when the root
+/// is moved; a dangling one is taken to mean the sibling of the same name.
 pub(crate) fn resolve_symlink(link: &Path) -> Option<PathBuf> {
     let raw = fs::read_link(link).ok()?;
-    if raw.is_absolute() {
-        Some(raw)
-    } else {
-        link.parent().map(|p| p.join(&raw)).or(Some(raw))
+    let parent = link.parent();
+    if !raw.is_absolute() {
+        return parent.map(|p| p.join(&raw)).or(Some(raw));
+    }
+    if !raw.exists() {
+        if let Some(moved) = parent.zip(raw.file_name()).map(|(p, n)| p.join(n)) {
+            if moved.exists() {
+                return Some(moved);
+            }
+        }
     }
+    Some(raw)
 }
 
 /// Lexically normalize and compare two paths (handles `.` / `..` / duplicate
@@ -282,3 +502,47 @@ fn make_all_files_executable(path: &Path) -> io::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::CachedHttpClient;
+    use crate::test_server;
+
+    #[test]
+    fn discards_what_was_streamed_when_the_checksum_fails() {
+        let root = env::temp_dir().join(format!("rsdk-streamed-{}", uuid::Uuid::new_v4()));
+        let home = RsdkHome::at(root.clone()).unwrap();
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "tool-1.0/bin/tool", &b"#!sh"[..])
+            .unwrap();
+        let tarball = builder.into_inner().unwrap();
+        let bad_checksum = ("X-Sdkman-Checksum-SHA-256".to_string(), "0".repeat(64));
+        let base_url =
+            test_server::serve(move |_| (200, vec![bad_checksum.clone()], tarball.clone()));
+
+        let client = CachedHttpClient::new(&home.cache());
+        let tv = ToolVersion::new(&home, "tool", "1.0");
+        let work_dir = home.temp().join("work");
+        let mut extracted = false;
+        let err = tv
+            .download_and_install(&work_dir, &Layout::Archive, |consume| {
+                let url = format!("{base_url}/tool-1.0.tar");
+                let archive = client.get_cached_file(&url, "tool", "1.0", consume);
+                extracted = work_dir.join("tool-1.0/bin/tool").exists();
+                archive
+            })
+            .unwrap_err();
+
+        // Extracted as it arrived, then dropped without being installed.
+        assert!(extracted);
+        assert!(format!("{err:#}").contains("checksum"), "{err:#}");
+        assert!(!work_dir.exists());
+        assert!(!tv.is_installed());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
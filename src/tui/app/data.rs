@@ -1,7 +1,7 @@
 //! Loading the tools and versions lists from the SDKMAN API and the local
 //! install home.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use color_eyre::Result;
 
@@ -71,7 +71,17 @@ impl App {
     }
 
     pub(super) fn load_versions(&mut self, tool: &str) -> Result<()> {
-        let all = self.sdkman.get_tool_versions(tool)?;
+        // Java versions are grouped by vendor.
+        let mut vendors: HashMap<String, String> = HashMap::new();
+        let all = if tool == "java" {
+            let entries = self.sdkman.get_java_versions()?;
+            for e in &entries {
+                vendors.insert(e.distribution.clone(), e.vendor.clone());
+            }
+            entries.into_iter().map(|e| e.identifier).collect()
+        } else {
+            self.sdkman.get_tool_versions(tool)?
+        };
         let installed: Vec<ToolVersion> =
             self.rsdk_home.installed_versions(tool)?.collect::<Vec<_>>();
 
@@ -91,7 +101,11 @@ impl App {
             .map(|v| {
                 let installed_tv = installed.iter().find(|tv| tv.version == v);
                 let starred = installed_tv.is_some();
+                let vendor = ToolVersion::new(&self.rsdk_home, tool, &v)
+                    .vendor()
+                    .map(|d| vendors.get(d).cloned().unwrap_or_else(|| d.to_string()));
                 let mut item = Item::new(v, starred);
+                item.group = vendor;
                 if let Some(tv) = installed_tv {
                     item.is_current = tv.is_current();
                     item.is_default = tv.is_default();
@@ -7,9 +7,9 @@ use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListItem as RIte
 use ratatui::Frame;
 
 use crate::tui::theme::{
-    border_block, border_block_active, highlight_style, item_line, C_ACCENT, C_BORDER, C_DIM,
-    C_ERROR, C_INFO, C_MODAL_BORDER, C_PROGRESS, C_SEARCH, C_STAR, C_STATUS_BG, C_STATUS_FG,
-    C_TITLE_BG, C_TITLE_FG,
+    border_block, border_block_active, grouped_item_lines, highlight_style, item_line, C_ACCENT,
+    C_BORDER, C_DIM, C_ERROR, C_INFO, C_MODAL_BORDER, C_PROGRESS, C_SEARCH, C_STAR, C_STATUS_BG,
+    C_STATUS_FG, C_TITLE_BG, C_TITLE_FG,
 };
 use crate::tui::{filter_items, Item, ModalState, Progress};
 
@@ -82,9 +82,9 @@ impl App {
             let right_block = border_block_active("Versions");
             let right_items = filter_items(&self.versions, &self.search);
             let right_list = List::new(
-                right_items
-                    .iter()
-                    .map(|i| RItem::new(item_line(i)))
+                grouped_item_lines(&right_items)
+                    .into_iter()
+                    .map(RItem::new)
                     .collect::<Vec<_>>(),
             )
             .block(right_block)
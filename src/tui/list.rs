@@ -1,5 +1,7 @@
 //! List items shared by the panes and the action modals.
 
+use rsdk::version;
+
 #[derive(Debug, Clone)]
 pub(crate) struct Item {
     pub(crate) name: String,
@@ -8,6 +10,8 @@ pub(crate) struct Item {
     pub(crate) is_current: bool,
     /// True if this version is the `default` symlink target.
     pub(crate) is_default: bool,
+    /// Group label shown in its own column (the vendor of Java versions).
+    pub(crate) group: Option<String>,
 }
 
 impl Item {
@@ -17,12 +21,14 @@ impl Item {
             starred,
             is_current: false,
             is_default: false,
+            group: None,
         }
     }
 }
 
 /// Sort items: installed first (default → current → other installed, each by
-/// version descending), then uninstalled (version descending).
+/// version descending), then uninstalled grouped by `group` (version
+/// descending within a group).
 pub(crate) fn sort_items(items: &mut [Item]) {
     items.sort_by(|a, b| {
         // Installed (starred) bubble to top.
@@ -37,8 +43,9 @@ pub(crate) fn sort_items(items: &mut [Item]) {
                 };
                 rank(a).cmp(&rank(b))
             })
+            .then_with(|| a.group.cmp(&b.group))
             // Within the same rank: version descending (latest first).
-            .then_with(|| b.name.cmp(&a.name))
+            .then_with(|| version::compare(&b.name, &a.name))
     });
 }
 
@@ -49,7 +56,12 @@ pub(crate) fn filter_items(items: &[Item], query: &str) -> Vec<Item> {
     let q = query.to_lowercase();
     items
         .iter()
-        .filter(|i| i.name.to_lowercase().contains(&q))
+        .filter(|i| {
+            i.name.to_lowercase().contains(&q)
+                || i.group
+                    .as_ref()
+                    .is_some_and(|g| g.to_lowercase().contains(&q))
+        })
         .cloned()
         .collect()
 }
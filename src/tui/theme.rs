@@ -86,3 +86,33 @@ pub(crate) fn item_line(item: &Item) -> Line<'_> {
     }
     Line::from(spans)
 }
+
+/// Lines for `items` with their group label in a leading column, printed only
+/// on the first item of each run so the list reads as grouped.
+pub(crate) fn grouped_item_lines(items: &[Item]) -> Vec<Line<'_>> {
+    let width = items
+        .iter()
+        .filter_map(|i| i.group.as_ref())
+        .map(|g| g.chars().count())
+        .max();
+    let Some(width) = width else {
+        return items.iter().map(item_line).collect();
+    };
+    let mut previous: Option<&String> = None;
+    items
+        .iter()
+        .map(|item| {
+            let label = match &item.group {
+                Some(g) if previous != Some(g) => g.as_str(),
+                _ => "",
+            };
+            previous = item.group.as_ref();
+            let mut spans = vec![Span::styled(
+                format!("{label:width$}  "),
+                Style::default().fg(C_DIM),
+            )];
+            spans.extend(item_line(item).spans);
+            Line::from(spans)
+        })
+        .collect()
+}
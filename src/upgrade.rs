@@ -0,0 +1,139 @@
+//! Finding installed tool versions that have a newer release, for the
+//! `outdated` and `upgrade` commands.
+
+use crate::rsdk_home::RsdkHome;
+use crate::sdkman_client::SdkManClient;
+use crate::tool_version::ToolVersion;
+use crate::version::Version;
+use color_eyre::Result;
+
+/// How far an upgrade may move a Java version. Other tools always move to
+/// their latest stable release.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Scope {
+    /// Allow a newer feature release (17 → 21).
+    pub any_major: bool,
+    /// Allow switching to another vendor's build.
+    pub any_vendor: bool,
+}
+
+/// An installed version (the current or default one) with a newer release
+/// available.
+#[derive(Debug)]
+pub struct Outdated {
+    pub installed: ToolVersion,
+    pub latest: String,
+    /// What SDKMAN would install by default, for reference.
+    pub sdkman_default: Option<String>,
+}
+
+/// The current and default versions of `tool` (or of every installed tool)
+/// that have a newer release within `scope`.
+pub fn outdated(
+    home: &RsdkHome,
+    api: &SdkManClient,
+    tool: Option<&str>,
+    scope: Scope,
+) -> Result<Vec<Outdated>> {
+    let mut tools: Vec<String> = home.all_installed()?.map(|tv| tv.tool).collect();
+    tools.sort();
+    tools.dedup();
+    tools.retain(|t| tool.is_none_or(|tool| tool == t));
+
+    let mut outdated = vec![];
+    for tool in tools {
+        let mut baselines: Vec<ToolVersion> = home.default_version(&tool)?.into_iter().collect();
+        if let Some(current) = home.current_version(&tool)? {
+            if !baselines.contains(&current) {
+                baselines.push(current);
+            }
+        }
+        if baselines.is_empty() {
+            continue;
+        }
+
+        let available = api.get_tool_versions(&tool)?;
+        let sdkman_default = api.get_default_version(&tool).ok();
+        for installed in baselines {
+            if let Some(latest) = newest(&tool, &installed.version, &available, scope) {
+                outdated.push(Outdated {
+                    installed,
+                    latest,
+                    sdkman_default: sdkman_default.clone(),
+                });
+            }
+        }
+    }
+    Ok(outdated)
+}
+
+/// The newest of `available` that is newer than `version`. Pre-releases are
+/// only considered when `version` is one itself.
+fn newest(tool: &str, version: &str, available: &[String], scope: Scope) -> Option<String> {
+    let current = Version::parse(version);
+    available
+        .iter()
+        .map(|v| Version::parse(v))
+        .filter(|v| *v > current)
+        .filter(|v| current.is_pre_release() || !v.is_pre_release())
+        .filter(|v| tool != "java" || scope.any_vendor || v.vendor() == current.vendor())
+        .filter(|v| tool != "java" || scope.any_major || v.major() == current.major())
+        .max()
+        .map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn java_stays_within_vendor_and_major() {
+        let available: Vec<String> = [
+            "21.0.7-tem",
+            "21.0.8-amzn",
+            "24.0.1-tem",
+            "25.ea.3-tem",
+            "17.0.15-tem",
+        ]
+        .map(String::from)
+        .into();
+        let newest = |version, scope| newest("java", version, &available, scope);
+
+        assert_eq!(
+            newest("21.0.6-tem", Scope::default()).as_deref(),
+            Some("21.0.7-tem")
+        );
+        assert_eq!(newest("21.0.7-tem", Scope::default()), None);
+        let any_major = Scope {
+            any_major: true,
+            ..Scope::default()
+        };
+        assert_eq!(
+            newest("21.0.6-tem", any_major).as_deref(),
+            Some("24.0.1-tem")
+        );
+        let any_vendor = Scope {
+            any_vendor: true,
+            ..Scope::default()
+        };
+        assert_eq!(
+            newest("21.0.6-tem", any_vendor).as_deref(),
+            Some("21.0.8-amzn")
+        );
+    }
+
+    #[test]
+    fn other_tools_move_to_latest_stable() {
+        let available: Vec<String> = ["3.9.9", "3.9.10", "4.0.0-rc-2", "3.8.8"]
+            .map(String::from)
+            .into();
+        assert_eq!(
+            newest("maven", "3.8.8", &available, Scope::default()).as_deref(),
+            Some("3.9.10")
+        );
+        assert_eq!(
+            newest("maven", "3.9.10", &available, Scope::default()),
+            None
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! Ordering of SDKMAN version identifiers: `21.0.6-tem`, `8.0.402-amzn`,
+//! `22.3.r17-grl`, `4.0.0-M1`, `3.9.0-rc-1`, `25.ea.1-open`...
+//!
+//! An identifier is read as numeric release segments (`21.0.6`), optional
+//! qualifiers (`rc-1`, `M1`, `ea.1`, `fx`) and, for Java, a trailing vendor
+//! code (`tem`). Releases compare numerically, so `21.0.6` ranks above
+//! `9.0.1`. Pre-release qualifiers rank below the plain release, other
+//! qualifiers (variants such as `fx` or `crac`) above it.
+//!
+//! Specifiers (`21`, `3.9.x`, `21-tem`, `latest`, `lts`, `lts-tem`) pick the
+//! best of a list of identifiers, see [`resolve`].
+
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone)]
+pub struct Version {
+    raw: String,
+    release: Vec<u64>,
+    qualifier: Vec<Token>,
+    vendor: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Token {
+    Number(u64),
+    Word(String),
+}
+
+/// Qualifiers marking a version as not released yet, in ascending order.
+const PRE_RELEASES: &[&[&str]] = &[
+    &["ea", "alpha", "a"],
+    &["beta", "b"],
+    &["milestone", "m"],
+    &["rc", "cr", "preview"],
+    &["snapshot"],
+];
+
+/// Qualifiers meaning "the release itself".
+const RELEASES: &[&str] = &["ga", "final", "release"];
+
+impl Version {
+    pub fn parse(raw: &str) -> Version {
+        let (rest, vendor) = match raw.rsplit_once('-') {
+            Some((rest, suffix)) if is_vendor(suffix) => (rest, Some(suffix.to_string())),
+            _ => (raw, None),
+        };
+
+        let mut tokens = tokenize(rest).into_iter().peekable();
+        let mut release = vec![];
+        while let Some(Token::Number(n)) = tokens.peek() {
+            release.push(*n);
+            tokens.next();
+        }
+        // Trailing zeros don't matter: 8.0 == 8.0.0.
+        while release.last() == Some(&0) {
+            release.pop();
+        }
+        let qualifier = tokens
+            .filter(|t| !matches!(t, Token::Word(w) if RELEASES.contains(&w.as_str())))
+            .collect();
+
+        Version {
+            raw: raw.to_string(),
+            release,
+            qualifier,
+            vendor,
+        }
+    }
+
+    /// The vendor code ending the identifier, e.g. `tem` in `21.0.6-tem`.
+    pub fn vendor(&self) -> Option<&str> {
+        self.vendor.as_deref()
+    }
+
+    /// The leading release number (`21` in `21.0.6-tem`).
+    pub fn major(&self) -> Option<u64> {
+        self.release.first().copied()
+    }
+
+    /// True for early-access, alpha, beta, milestone, release-candidate and
+    /// snapshot versions.
+    pub fn is_pre_release(&self) -> bool {
+        matches!(self.qualifier.first(), Some(Token::Word(w)) if pre_release_rank(w).is_some())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// True if the release starts with `prefix` (`21.0.6` starts with `21`
+    /// and `21.0`). Missing segments count as zeros.
+    fn has_release_prefix(&self, prefix: &[u64]) -> bool {
+        prefix
+            .iter()
+            .enumerate()
+            .all(|(i, n)| self.release.get(i).copied().unwrap_or(0) == *n)
+    }
+}
+
+/// What a version specifier asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Spec {
+    Latest,
+    Lts,
+    /// Leading release segments: `21`, `3.9` (also written `3.9.x`).
+    Prefix(Vec<u64>),
+}
+
+/// Parse `spec` into what it asks for and an optional vendor (`lts-tem`,
+/// `21-tem`). `None` if it is not a specifier.
+fn parse_spec(spec: &str) -> Option<(Spec, Option<String>)> {
+    let spec = spec.to_lowercase();
+    let (base, vendor) = match spec.split_once('-') {
+        Some((base, vendor)) if is_vendor(vendor) => (base, Some(vendor.to_string())),
+        Some(_) => return None,
+        None => (spec.as_str(), None),
+    };
+    let kind = match base {
+        "latest" => Spec::Latest,
+        "lts" => Spec::Lts,
+        _ => {
+            let numbers = base
+                .strip_suffix(".x")
+                .or_else(|| base.strip_suffix(".*"))
+                .unwrap_or(base);
+            let prefix = numbers
+                .split('.')
+                .map(|n| n.parse().ok())
+                .collect::<Option<Vec<u64>>>()?;
+            Spec::Prefix(prefix)
+        }
+    };
+    Some((kind, vendor))
+}
+
+/// True if `spec` is a specifier to [`resolve`] rather than an identifier
+/// to use as is: `latest`, `lts`, a partial release (`21`, `3.9`), or an
+/// explicit wildcard (`3.9.x`). Full releases like `3.9.9` or `21.0.6-tem`
+/// are identifiers.
+pub fn is_specifier(spec: &str) -> bool {
+    let wildcard = spec.ends_with(".x") || spec.ends_with(".*");
+    match parse_spec(spec) {
+        Some((Spec::Prefix(prefix), _)) => wildcard || prefix.len() < 3,
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// Pick the version `spec` designates among `candidates`: the highest
+/// matching one, preferring stable releases over pre-releases, and versions
+/// of `preferred_vendor` when the specifier names no vendor. An identifier
+/// present in `candidates` designates itself.
+///
+/// For `lts`, `is_lts` tells whether an identifier is a long-term support
+/// release (only meaningful for Java).
+pub fn resolve<'a>(
+    spec: &str,
+    candidates: &'a [String],
+    preferred_vendor: Option<&str>,
+    is_lts: impl Fn(&str) -> bool,
+) -> Option<&'a String> {
+    if let Some(exact) = candidates.iter().find(|c| c.as_str() == spec) {
+        return Some(exact);
+    }
+    let (kind, vendor) = parse_spec(spec)?;
+    let matching: Vec<(&String, Version)> = candidates
+        .iter()
+        .map(|c| (c, Version::parse(c)))
+        .filter(|(_, v)| vendor.is_none() || v.vendor() == vendor.as_deref())
+        .filter(|(c, v)| match &kind {
+            Spec::Latest => true,
+            Spec::Lts => is_lts(c),
+            Spec::Prefix(prefix) => v.has_release_prefix(prefix),
+        })
+        .collect();
+
+    let preferred: Vec<_> = matching
+        .iter()
+        .filter(|(_, v)| {
+            vendor.is_none() && preferred_vendor.is_some() && v.vendor() == preferred_vendor
+        })
+        .collect();
+    let pool: Vec<_> = if preferred.is_empty() {
+        matching.iter().collect()
+    } else {
+        preferred
+    };
+    let stable: Vec<_> = pool.iter().filter(|(_, v)| !v.is_pre_release()).collect();
+    let pool: Vec<_> = if stable.is_empty() {
+        pool.iter().collect()
+    } else {
+        stable
+    };
+    pool.into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1))
+        .map(|(c, _)| *c)
+}
+
+/// Rank of the qualifier relative to the plain release: pre-releases below,
+/// variants above.
+fn qualifier_rank(qualifier: &[Token]) -> (i8, usize) {
+    match qualifier.first() {
+        None => (0, 0),
+        Some(Token::Word(w)) => match pre_release_rank(w) {
+            Some(rank) => (-1, rank),
+            None => (1, 0),
+        },
+        Some(Token::Number(_)) => (1, 0),
+    }
+}
+
+fn pre_release_rank(word: &str) -> Option<usize> {
+    PRE_RELEASES.iter().position(|names| names.contains(&word))
+}
+
+/// A trailing `-xyz` is a vendor code when it is a lowercase word that is not
+/// a known qualifier (`-rc`, `-beta`...).
+fn is_vendor(suffix: &str) -> bool {
+    !suffix.is_empty()
+        && suffix.chars().all(|c| c.is_ascii_lowercase())
+        && pre_release_rank(suffix).is_none()
+        && !RELEASES.contains(&suffix)
+        && suffix != "snapshot"
+}
+
+/// Split on separators and at digit/letter boundaries: `r17` → `r`, `17`.
+fn tokenize(s: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let flush = |current: &mut String, tokens: &mut Vec<Token>| {
+        if current.is_empty() {
+            return;
+        }
+        let token = match current.parse() {
+            Ok(n) => Token::Number(n),
+            Err(_) => Token::Word(current.to_lowercase()),
+        };
+        tokens.push(token);
+        current.clear();
+    };
+    for c in s.chars() {
+        if matches!(c, '.' | '-' | '_' | '+') {
+            flush(&mut current, &mut tokens);
+            continue;
+        }
+        let boundary = current
+            .chars()
+            .last()
+            .is_some_and(|last| last.is_ascii_digit() != c.is_ascii_digit());
+        if boundary {
+            flush(&mut current, &mut tokens);
+        }
+        current.push(c);
+    }
+    flush(&mut current, &mut tokens);
+    tokens
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.release
+            .cmp(&other.release)
+            .then_with(|| qualifier_rank(&self.qualifier).cmp(&qualifier_rank(&other.qualifier)))
+            .then_with(|| self.qualifier.cmp(&other.qualifier))
+            .then_with(|| self.vendor.cmp(&other.vendor))
+            // Keep `Ord` consistent with `Eq` for spellings like 8.0 / 8.0.0.
+            .then_with(|| self.raw.cmp(&other.raw))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Eq for Version {}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// Compare two identifiers as versions.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    Version::parse(a).cmp(&Version::parse(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(versions: &[&str]) -> Vec<String> {
+        let mut versions: Vec<_> = versions.iter().map(|v| Version::parse(v)).collect();
+        versions.sort();
+        versions.into_iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn orders_numerically() {
+        assert_eq!(
+            sorted(&[
+                "21.0.6-tem",
+                "9.0.1",
+                "8.0.402-tem",
+                "17.0.14-tem",
+                "21.0.10-tem"
+            ]),
+            [
+                "8.0.402-tem",
+                "9.0.1",
+                "17.0.14-tem",
+                "21.0.6-tem",
+                "21.0.10-tem"
+            ]
+        );
+    }
+
+    #[test]
+    fn pre_releases_rank_below_release() {
+        assert_eq!(
+            sorted(&[
+                "4.0.0",
+                "4.0.0-M1",
+                "4.0.0-rc-1",
+                "4.0.0-alpha-2",
+                "4.0.0-M2",
+                "3.9.9"
+            ]),
+            [
+                "3.9.9",
+                "4.0.0-alpha-2",
+                "4.0.0-M1",
+                "4.0.0-M2",
+                "4.0.0-rc-1",
+                "4.0.0"
+            ]
+        );
+        assert!(Version::parse("25.ea.1-open").is_pre_release());
+        assert!(Version::parse("25.ea.1-open") < Version::parse("25-open"));
+        assert!(Version::parse("8.0.402.fx-zulu") > Version::parse("8.0.402-zulu"));
+    }
+
+    #[test]
+    fn resolves_specifiers() {
+        let java: Vec<String> = [
+            "25.ea.1-open",
+            "21.0.6-tem",
+            "21.0.5-tem",
+            "21.0.7-amzn",
+            "17.0.14-tem",
+            "17.0.15-zulu",
+            "8.0.402-tem",
+        ]
+        .map(String::from)
+        .into();
+        let lts = |v: &str| !v.starts_with("25.ea");
+        let pick = |spec, vendor| resolve(spec, &java, vendor, lts).map(String::as_str);
+
+        assert_eq!(pick("21", Some("tem")), Some("21.0.6-tem"));
+        assert_eq!(pick("21", None), Some("21.0.7-amzn"));
+        assert_eq!(pick("17-zulu", Some("tem")), Some("17.0.15-zulu"));
+        assert_eq!(pick("21.0.x", Some("tem")), Some("21.0.6-tem"));
+        assert_eq!(pick("latest", Some("tem")), Some("21.0.6-tem"));
+        assert_eq!(pick("lts-tem", None), Some("21.0.6-tem"));
+        assert_eq!(pick("25", None), Some("25.ea.1-open"));
+        assert_eq!(pick("8.0.402-tem", None), Some("8.0.402-tem"));
+        assert_eq!(pick("11", None), None);
+
+        let maven: Vec<String> = ["3.9.9", "3.9.10", "4.0.0-rc-2", "3.8.8"]
+            .map(String::from)
+            .into();
+        let pick = |spec| resolve(spec, &maven, None, |_| false).map(String::as_str);
+        assert_eq!(pick("3.9"), Some("3.9.10"));
+        assert_eq!(pick("3.9.x"), Some("3.9.10"));
+        assert_eq!(pick("latest"), Some("3.9.10"));
+        assert_eq!(pick("4"), Some("4.0.0-rc-2"));
+
+        assert!(is_specifier("21") && is_specifier("3.9.x") && is_specifier("lts-tem"));
+        assert!(!is_specifier("3.9.9") && !is_specifier("21.0.6-tem") && !is_specifier("4.0.0-M1"));
+    }
+
+    #[test]
+    fn splits_vendor_suffix() {
+        let v = Version::parse("22.3.r17-grl");
+        assert_eq!(v.vendor(), Some("grl"));
+        assert_eq!(v.major(), Some(22));
+        assert_eq!(Version::parse("3.9.0-rc").vendor(), None);
+        assert_eq!(Version::parse("3.9.9").vendor(), None);
+        assert_eq!(compare("8.0-tem", "8.0.0-tem"), Ordering::Less);
+    }
+}
@@ -12,3 +12,15 @@ rsdk() {
     rm -f "$temp_file"
     return "$status"
 }
+
+# auto_env: at the first prompt and whenever $PWD changed since the last one,
+# if RSDK_AUTO_ENV is exported (`rsdk init` does it).
+_rsdk_auto_env() {
+    [ "$PWD" = "${_rsdk_auto_env_pwd-}" ] && return
+    _rsdk_auto_env_pwd="$PWD"
+    [ -n "${RSDK_AUTO_ENV-}" ] && rsdk env auto
+}
+case ";${PROMPT_COMMAND-};" in
+    *";_rsdk_auto_env;"*) ;;
+    *) PROMPT_COMMAND="_rsdk_auto_env${PROMPT_COMMAND:+;$PROMPT_COMMAND}" ;;
+esac
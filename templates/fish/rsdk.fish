@@ -15,3 +15,10 @@ function rsdk
     rm -f $temp_file
     return $command_status
 end
+
+# auto_env: whenever $PWD changes and for the starting directory, if
+# RSDK_AUTO_ENV is exported (`rsdk init` does it).
+function __rsdk_auto_env --on-variable PWD
+    set -q RSDK_AUTO_ENV; and rsdk env auto
+end
+__rsdk_auto_env
@@ -9,7 +9,7 @@ use std::sync::Mutex;
 
 use rsdk::rcfile;
 use rsdk::rsdk_home::RsdkHome;
-use rsdk::tool_version::ToolVersion;
+use rsdk::tool_version::{resolve_installed, ToolVersion};
 
 /// Serializes tests that mutate the process-wide current directory, which is
 /// shared global state and would otherwise race under `cargo test`'s default
@@ -29,8 +29,10 @@ fn fake_install(home: &RsdkHome, tool: &str, version: &str) -> ToolVersion {
     tv
 }
 
+/// The symlink's target, resolved against its directory when relative.
 fn read_link(path: &Path) -> PathBuf {
-    fs::read_link(path).expect("expected a symlink")
+    let target = fs::read_link(path).expect("expected a symlink");
+    path.parent().unwrap().join(target)
 }
 
 // --- current resolution fallback (legacy installs) -------------------------
@@ -84,6 +86,25 @@ fn env_var_outside_tool_dir_is_ignored() {
     assert!(resolved.is_none());
 }
 
+#[test]
+fn session_version_wins_over_current_symlink() {
+    // A session `use` points this shell's PATH at the version dir and leaves
+    // the shared `current` symlink alone.
+    let home = test_home();
+    let v17 = fake_install(&home, "java", "17-tem");
+    let v21 = fake_install(&home, "java", "21-tem");
+    v21.make_current().unwrap();
+
+    let path = env::join_paths([v17.bin(), PathBuf::from("/usr/bin")]).unwrap();
+    assert_eq!(home.session_version_in("java", &path), Some(v17.path()));
+    assert_eq!(home.resolve_current_in("java", &path), Some(v17.path()));
+
+    // The shared `current/bin` entry is not a session version.
+    let shared = env::join_paths([home.current_symlink_path("java").join("bin")]).unwrap();
+    assert_eq!(home.session_version_in("java", &shared), None);
+    assert_eq!(home.resolve_current_in("java", &shared), Some(v21.path()));
+}
+
 // --- current symlink model -------------------------------------------------
 
 #[test]
@@ -149,6 +170,41 @@ fn make_default_points_default_symlink_at_version() {
     assert_eq!(read_link(&home.default_symlink_path("maven")), tv.path());
 }
 
+#[test]
+fn symlinks_survive_moving_the_root() {
+    let home = test_home();
+    let tv = fake_install(&home, "java", "21-tem");
+    tv.make_default().unwrap();
+    tv.make_current().unwrap();
+    assert!(fs::read_link(home.current_symlink_path("java"))
+        .unwrap()
+        .is_relative());
+
+    let moved_root = env::temp_dir().join(format!("rsdk-test-{}", uuid::Uuid::new_v4()));
+    fs::rename(&home.root, &moved_root).unwrap();
+    let moved = RsdkHome::at(moved_root).unwrap();
+    let moved_tv = ToolVersion::new(&moved, "java", "21-tem");
+
+    assert!(moved_tv.is_current());
+    assert!(moved_tv.is_default());
+    assert!(moved.current_symlink_path("java").join("bin").is_dir());
+}
+
+#[test]
+fn relink_repairs_absolute_symlinks_after_a_move() {
+    let home = test_home();
+    let tv = fake_install(&home, "java", "21-tem");
+    let stale = PathBuf::from("/nonexistent/rsdk/tools/java/21-tem");
+    symlink::symlink_dir(&stale, home.default_symlink_path("java")).unwrap();
+
+    assert!(tv.is_default());
+    home.relink("java").unwrap();
+    assert_eq!(
+        fs::read_link(home.default_symlink_path("java")).unwrap(),
+        PathBuf::from("21-tem")
+    );
+}
+
 #[test]
 fn default_and_current_are_independent() {
     let home = test_home();
@@ -327,20 +383,45 @@ fn env_init_writes_current_versions_to_sdkmanrc() {
     maven.make_current().unwrap();
 
     in_temp_dir(|dir| {
-        rcfile::env_init(&home).unwrap();
+        rcfile::env_init(&home, None).unwrap();
         let written = fs::read_to_string(dir.join(".sdkmanrc")).unwrap();
         assert!(written.contains("java=21-tem"), "got: {written}");
         assert!(written.contains("maven=3.9.9"), "got: {written}");
     });
 }
 
+#[test]
+fn env_init_updates_existing_sdkmanrc_in_place() {
+    let home = test_home();
+    fake_install(&home, "java", "21-tem")
+        .make_current()
+        .unwrap();
+    fake_install(&home, "maven", "3.9.9")
+        .make_current()
+        .unwrap();
+
+    in_temp_dir(|dir| {
+        let original = "# build JDK\njava=17-tem\nsdkman_auto_env=true\n";
+        fs::write(dir.join(".sdkmanrc"), original).unwrap();
+        rcfile::env_init(&home, None).unwrap();
+        let written = fs::read_to_string(dir.join(".sdkmanrc")).unwrap();
+        assert_eq!(
+            written,
+            "# build JDK\njava=21-tem\nsdkman_auto_env=true\nmaven=3.9.9\n"
+        );
+
+        // SDKMAN settings are not tools.
+        rcfile::env_apply(&home, None).unwrap();
+    });
+}
+
 #[test]
 fn env_init_skips_tools_with_no_current() {
     let home = test_home();
     fake_install(&home, "java", "21-tem"); // installed but never made current
 
     in_temp_dir(|dir| {
-        rcfile::env_init(&home).unwrap();
+        rcfile::env_init(&home, None).unwrap();
         let written = fs::read_to_string(dir.join(".sdkmanrc")).unwrap();
         assert!(!written.contains("java"), "got: {written}");
     });
@@ -355,13 +436,53 @@ fn env_apply_switches_current_from_sdkmanrc() {
 
     in_temp_dir(|dir| {
         fs::write(dir.join(".sdkmanrc"), "java=17-tem\n").unwrap();
-        rcfile::env_apply(&home).unwrap();
+        rcfile::env_apply(&home, None).unwrap();
     });
 
     assert!(v17.is_current());
     assert!(!v21.is_current());
 }
 
+#[test]
+fn env_apply_resolves_version_specifiers() {
+    let home = test_home();
+    let v17 = fake_install(&home, "java", "17.0.14-tem");
+    fake_install(&home, "java", "21.0.5-tem");
+    let v21 = fake_install(&home, "java", "21.0.6-tem");
+    v17.make_current().unwrap();
+
+    in_temp_dir(|dir| {
+        fs::write(dir.join(".sdkmanrc"), "java=21\n").unwrap();
+        rcfile::env_apply(&home, None).unwrap();
+    });
+
+    assert!(v21.is_current());
+}
+
+#[test]
+fn resolve_installed_picks_highest_match() {
+    let home = test_home();
+    fake_install(&home, "maven", "3.9.9");
+    fake_install(&home, "maven", "3.9.10");
+    fake_install(&home, "maven", "3.8.8");
+
+    let resolve = |spec| {
+        resolve_installed(&home, "maven", spec)
+            .unwrap()
+            .map(|tv| tv.version)
+    };
+    assert_eq!(resolve("3.9").as_deref(), Some("3.9.10"));
+    assert_eq!(resolve("3.8.x").as_deref(), Some("3.8.8"));
+    assert_eq!(resolve("latest").as_deref(), Some("3.9.10"));
+    assert_eq!(resolve("3.9.9").as_deref(), Some("3.9.9"));
+    assert_eq!(resolve("4"), None);
+    assert_eq!(resolve("3.9.11"), None);
+
+    // A release named like a partial one designates itself.
+    fake_install(&home, "maven", "3.8");
+    assert_eq!(resolve("3.8").as_deref(), Some("3.8"));
+}
+
 #[test]
 fn env_apply_switches_multiple_tools() {
     let home = test_home();
@@ -374,7 +495,7 @@ fn env_apply_switches_multiple_tools() {
 
     in_temp_dir(|dir| {
         fs::write(dir.join(".sdkmanrc"), "java=17-tem\nmaven=3.8.8\n").unwrap();
-        rcfile::env_apply(&home).unwrap();
+        rcfile::env_apply(&home, None).unwrap();
     });
 
     assert!(java17.is_current());
@@ -388,7 +509,7 @@ fn env_apply_errors_when_tool_not_installed() {
     let home = test_home();
     in_temp_dir(|dir| {
         fs::write(dir.join(".sdkmanrc"), "java=11-tem\n").unwrap();
-        let err = rcfile::env_apply(&home).unwrap_err();
+        let err = rcfile::env_apply(&home, None).unwrap_err();
         assert!(err.to_string().contains("not installed"), "got: {err}");
     });
 }
@@ -397,11 +518,95 @@ fn env_apply_errors_when_tool_not_installed() {
 fn env_apply_errors_without_sdkmanrc() {
     let home = test_home();
     in_temp_dir(|_| {
-        let err = rcfile::env_apply(&home).unwrap_err();
+        let err = rcfile::env_apply(&home, None).unwrap_err();
         assert!(err.to_string().contains(".sdkmanrc"), "got: {err}");
     });
 }
 
+#[test]
+fn env_apply_finds_sdkmanrc_in_parent_dirs() {
+    let home = test_home();
+    let v17 = fake_install(&home, "java", "17-tem");
+    fake_install(&home, "java", "21-tem")
+        .make_current()
+        .unwrap();
+
+    in_temp_dir(|dir| {
+        fs::write(dir.join(".sdkmanrc"), "java=17-tem\n").unwrap();
+        let nested = dir.join("module").join("src");
+        fs::create_dir_all(&nested).unwrap();
+        env::set_current_dir(&nested).unwrap();
+        rcfile::env_apply(&home, None).unwrap();
+    });
+
+    assert!(v17.is_current());
+}
+
+#[test]
+fn sdkmanrc_discovery_stops_at_git_root() {
+    in_temp_dir(|dir| {
+        fs::write(dir.join(".sdkmanrc"), "java=17-tem\n").unwrap();
+        let repo = dir.join("repo");
+        let nested = repo.join("src");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(rcfile::find(&nested), None);
+
+        fs::write(repo.join(".sdkmanrc"), "java=21-tem\n").unwrap();
+        assert_eq!(rcfile::find(&nested), Some(repo.join(".sdkmanrc")));
+    });
+}
+
+#[test]
+fn env_apply_reads_explicit_file() {
+    let home = test_home();
+    let v17 = fake_install(&home, "java", "17-tem");
+    fake_install(&home, "java", "21-tem")
+        .make_current()
+        .unwrap();
+
+    in_temp_dir(|dir| {
+        let file = dir.join("java17.sdkmanrc");
+        fs::write(&file, "java=17-tem\n").unwrap();
+        rcfile::env_apply(&home, Some(&file)).unwrap();
+
+        let err = rcfile::env_apply(&home, Some(&dir.join("missing"))).unwrap_err();
+        assert!(err.to_string().contains("not found"), "got: {err}");
+    });
+
+    assert!(v17.is_current());
+}
+
+#[test]
+fn env_add_remove_and_pin_edit_one_entry() {
+    let home = test_home();
+    fake_install(&home, "java", "21.0.6-tem");
+    fake_install(&home, "maven", "3.9.9")
+        .make_current()
+        .unwrap();
+
+    in_temp_dir(|dir| {
+        let rc = dir.join(".sdkmanrc");
+        fs::write(&rc, "# tools\ngradle=8.11.1\n").unwrap();
+        rcfile::env_add(&home, None, "java", Some("21")).unwrap();
+        rcfile::env_add(&home, None, "maven", None).unwrap();
+        rcfile::env_remove(None, "gradle").unwrap();
+        assert_eq!(
+            fs::read_to_string(&rc).unwrap(),
+            "# tools\njava=21\nmaven=3.9.9\n"
+        );
+
+        rcfile::env_pin(&home, None).unwrap();
+        assert_eq!(
+            fs::read_to_string(&rc).unwrap(),
+            "# tools\njava=21.0.6-tem\nmaven=3.9.9\n"
+        );
+
+        let err = rcfile::env_remove(None, "gradle").unwrap_err();
+        assert!(err.to_string().contains("not in"), "got: {err}");
+    });
+}
+
 #[test]
 fn env_clear_restores_default_as_current() {
     let home = test_home();
@@ -0,0 +1,120 @@
+//! What session-scoped version changes emit for the shell wrapper. These set
+//! the process-wide `ARGS` (`--shell bash --envout <file>`), so they live in
+//! their own test binary and never touch the process `PATH`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use clap::Parser;
+use rsdk::args::{Cli, ARGS};
+use rsdk::rcfile;
+use rsdk::rsdk_home::RsdkHome;
+use rsdk::tool_version::ToolVersion;
+
+/// Serializes the tests, which share the `--envout` file.
+static ENVOUT_LOCK: Mutex<()> = Mutex::new(());
+
+/// The `--envout` file, set up once for the whole test binary.
+fn envout() -> &'static Path {
+    static ENVOUT: OnceLock<PathBuf> = OnceLock::new();
+    ENVOUT.get_or_init(|| {
+        let path = env::temp_dir().join(format!("rsdk-envout-{}", uuid::Uuid::new_v4()));
+        fs::write(&path, "").unwrap();
+        let cli = Cli::parse_from([
+            "rsdk",
+            "--shell",
+            "bash",
+            "--envout",
+            path.to_str().unwrap(),
+        ]);
+        let _ = ARGS.set(cli);
+        path
+    })
+}
+
+/// The statements `f` emits for the shell wrapper.
+fn emitted(f: impl FnOnce()) -> Vec<String> {
+    let _guard = ENVOUT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = envout();
+    fs::write(path, "").unwrap();
+    f();
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn test_home() -> RsdkHome {
+    let dir = env::temp_dir().join(format!("rsdk-test-{}", uuid::Uuid::new_v4()));
+    RsdkHome::at(dir).expect("failed to create test RsdkHome")
+}
+
+fn fake_install(home: &RsdkHome, tool: &str, version: &str) -> ToolVersion {
+    let tv = ToolVersion::new(home, tool, version);
+    fs::create_dir_all(tv.bin()).expect("failed to fabricate install");
+    tv
+}
+
+/// The value of the `PATH` export among `lines`.
+fn exported_path(lines: &[String]) -> String {
+    let path: Vec<&String> = lines
+        .iter()
+        .filter(|line| line.starts_with("export PATH="))
+        .collect();
+    assert_eq!(path.len(), 1, "expected a single PATH export in {lines:?}");
+    path[0]["export PATH=".len()..]
+        .trim_matches('"')
+        .to_string()
+}
+
+#[test]
+fn session_use_emits_the_version_bin_and_leaves_current_alone() {
+    let home = test_home();
+    let v17 = fake_install(&home, "java", "17-tem");
+    let v21 = fake_install(&home, "java", "21-tem");
+    v21.make_current().unwrap();
+
+    let lines = emitted(|| v17.make_session_current().unwrap());
+
+    assert!(lines.contains(&format!("export JAVA_HOME=\"{}\"", v17.path().display())));
+    let path = exported_path(&lines);
+    assert!(path.starts_with(&v17.bin().to_string_lossy().into_owned()));
+    assert_eq!(
+        home.session_version_in("java", path.as_ref()),
+        Some(v17.path())
+    );
+    assert_eq!(
+        home.resolve_current_in("java", path.as_ref()),
+        Some(v17.path())
+    );
+    assert_eq!(
+        fs::read_link(home.current_symlink_path("java")).unwrap(),
+        PathBuf::from("21-tem")
+    );
+}
+
+#[test]
+fn env_applies_every_tool_to_the_session_in_one_path() {
+    let home = test_home();
+    let java = fake_install(&home, "java", "21-tem");
+    let maven = fake_install(&home, "maven", "3.9.9");
+    let file = home.root.join(".sdkmanrc");
+    fs::write(&file, "java=21\nmaven=3.9.9\n").unwrap();
+
+    let lines = emitted(|| rcfile::env_apply(&home, Some(&file)).unwrap());
+
+    let path = exported_path(&lines);
+    assert_eq!(
+        home.session_version_in("java", path.as_ref()),
+        Some(java.path())
+    );
+    assert_eq!(
+        home.session_version_in("maven", path.as_ref()),
+        Some(maven.path())
+    );
+    assert!(!home.current_symlink_path("java").exists());
+    assert!(!home.current_symlink_path("maven").exists());
+}